@@ -12,6 +12,7 @@ use profuzz_core::cli::ProFuzzBuilder;
 use std::fs::File;
 use std::io::Write;
 use std::net::Ipv4Addr;
+use std::process::ExitCode;
 use std::str::FromStr;
 
 /// Create a sample corpus packet
@@ -32,7 +33,7 @@ fn get_example_corpus() -> EtherMut {
 }
 
 #[tokio::main]
-async fn main() {
+async fn main() -> ExitCode {
     std::fs::create_dir_all("profuzz_pnet/corpus/").unwrap();
     let mut file = File::create("profuzz_pnet/corpus/example.bin").unwrap();
     file.write_all(&get_example_corpus().build().unwrap())
@@ -77,7 +78,5 @@ async fn main() {
     let fuzzer = ProFuzzBuilder::new(transport, healthcheck, DummyResetHandler());
 
     // Starting in the cli mode, so the user can start either the fuzzer or the triaging.
-    if let Err(err) = fuzzer.start_cli::<EtherMutatorOwned>().await {
-        eprintln!("{err}");
-    }
+    fuzzer.start_cli::<EtherMutatorOwned>().await
 }
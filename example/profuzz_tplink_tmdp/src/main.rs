@@ -1,8 +1,9 @@
 #![allow(unexpected_cfgs)]
 
 use std::collections::HashSet;
+use std::process::ExitCode;
 
-use pnet::packet::MutablePacket;
+use pnet::packet::{MutablePacket, Packet};
 use pnet_macros::packet;
 use pnet_macros_support::types::{u16be, u32be};
 use pnet_show::Show;
@@ -92,6 +93,51 @@ impl Corpus for TetherLayer {
         }
         self.buf
     }
+
+    // the length and the crc32 are not listed, as they are recomputed by `build`
+    fn fields(&self) -> Vec<(String, String)> {
+        let Some(pnet) = TetherPacket::new(&self.buf) else {
+            return vec![];
+        };
+        vec![
+            ("version".to_string(), pnet.get_version().to_string()),
+            ("unknown0".to_string(), pnet.get_unknown0().to_string()),
+            ("unknown1".to_string(), pnet.get_unknown1().to_string()),
+            ("unknown2".to_string(), pnet.get_unknown2().to_string()),
+            ("unknown3".to_string(), pnet.get_unknown3().to_string()),
+            ("options".to_string(), pnet.get_options().to_string()),
+            (
+                "function_id".to_string(),
+                pnet.get_function_id().to_string(),
+            ),
+            ("payload".to_string(), hex::encode(pnet.payload())),
+        ]
+    }
+
+    fn copy_field(&mut self, name: &str, other: &Self) -> bool {
+        let Some(other) = TetherPacket::new(&other.buf) else {
+            return false;
+        };
+        if name == "payload" {
+            self.buf.truncate(TetherPacket::minimum_packet_size());
+            self.buf.extend_from_slice(other.payload());
+            return true;
+        }
+        let Some(mut pnet) = MutableTetherPacket::new(&mut self.buf) else {
+            return false;
+        };
+        match name {
+            "version" => pnet.set_version(other.get_version()),
+            "unknown0" => pnet.set_unknown0(other.get_unknown0()),
+            "unknown1" => pnet.set_unknown1(other.get_unknown1()),
+            "unknown2" => pnet.set_unknown2(other.get_unknown2()),
+            "unknown3" => pnet.set_unknown3(other.get_unknown3()),
+            "options" => pnet.set_options(other.get_options()),
+            "function_id" => pnet.set_function_id(other.get_function_id()),
+            _ => return false,
+        }
+        true
+    }
 }
 
 impl Mutable for TetherLayer {
@@ -174,7 +220,7 @@ impl ResetHandler for TetherResetHandler {
 }
 
 #[tokio::main]
-async fn main() {
+async fn main() -> ExitCode {
     let tcp_config = TcpConfig {
         read_timeout: Some(500), // target does not always return something...
         write_timeout: 500,
@@ -195,9 +241,7 @@ async fn main() {
     let resethandler = TetherResetHandler;
 
     let fuzzer = ProFuzzBuilder::new(transport, healthcheck, resethandler);
-    if let Err(err) = fuzzer.start_cli::<TetherLayer>().await {
-        eprintln!("{err}");
-    }
+    fuzzer.start_cli::<TetherLayer>().await
 }
 
 #[cfg(test)]
@@ -0,0 +1,8 @@
+/// Collects the last bytes of a log file
+pub mod file_tail;
+
+/// Collects the output of a command
+pub mod command;
+
+/// Copies a file like a core dump
+pub mod file_copy;
@@ -0,0 +1,68 @@
+use profuzz_core::error::ProFuzzError;
+use profuzz_core::fuzz::control::CrashInfo;
+use profuzz_core::traits::CrashCollector;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::runtime::Handle;
+
+/// Runs a shell command and stores its stdout, e.g. to dump the state of the target over a
+/// debug interface. The crash is passed with the environment variables `PROFUZZ_CRASH_ID`,
+/// `PROFUZZ_WORKER` and `PROFUZZ_TARGET`. The command is killed after 30 seconds, see
+/// `with_timeout`.
+pub struct CommandCollector {
+    name: String,
+    command: String,
+    timeout: Duration,
+}
+
+impl CommandCollector {
+    /// Creates a collector which stores the stdout of `command` as artifact `name`
+    #[must_use]
+    pub fn new(name: &str, command: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            command: command.to_string(),
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    /// Sets the time after which the command is killed
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl CrashCollector for CommandCollector {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn collect(&mut self, crash: &CrashInfo) -> Result<Vec<u8>, ProFuzzError> {
+        let handle = Handle::try_current().map_err(|err| ProFuzzError::Custom {
+            err_msg: err.to_string(),
+        })?;
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .env("PROFUZZ_CRASH_ID", crash.id.to_string())
+            .env("PROFUZZ_WORKER", crash.worker.to_string())
+            .env("PROFUZZ_TARGET", &crash.target)
+            .kill_on_drop(true)
+            .output();
+        // the engine calls the collectors on the blocking thread pool, so the runtime can be
+        // blocked on here
+        let output = handle
+            .block_on(async { tokio::time::timeout(self.timeout, output).await })
+            .map_err(|elapsed| ProFuzzError::Timeout { elapsed })??;
+        if !output.status.success() {
+            tracing::warn!(
+                "Command of collector {} exited with {}",
+                self.name,
+                output.status
+            );
+        }
+        Ok(output.stdout)
+    }
+}
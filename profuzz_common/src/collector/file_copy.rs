@@ -0,0 +1,30 @@
+use profuzz_core::error::ProFuzzError;
+use profuzz_core::fuzz::control::CrashInfo;
+use profuzz_core::traits::CrashCollector;
+use std::path::PathBuf;
+
+/// Copies a file, e.g. a core dump written by the target.
+pub struct FileCopyCollector {
+    path: PathBuf,
+}
+
+impl FileCopyCollector {
+    /// Creates a collector which copies the file at `path`
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl CrashCollector for FileCopyCollector {
+    fn name(&self) -> String {
+        self.path.file_name().map_or_else(
+            || "file_copy".to_string(),
+            |name| name.to_string_lossy().to_string(),
+        )
+    }
+
+    fn collect(&mut self, _crash: &CrashInfo) -> Result<Vec<u8>, ProFuzzError> {
+        Ok(std::fs::read(&self.path)?)
+    }
+}
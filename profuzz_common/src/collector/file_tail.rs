@@ -0,0 +1,42 @@
+use profuzz_core::error::ProFuzzError;
+use profuzz_core::fuzz::control::CrashInfo;
+use profuzz_core::traits::CrashCollector;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+
+/// Stores the last `max_bytes` of a file, e.g. a serial log of the target written by another
+/// process.
+pub struct FileTailCollector {
+    path: PathBuf,
+    max_bytes: u64,
+}
+
+impl FileTailCollector {
+    /// Creates a collector which stores the last `max_bytes` of the file at `path`
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>, max_bytes: u64) -> Self {
+        Self {
+            path: path.into(),
+            max_bytes,
+        }
+    }
+}
+
+impl CrashCollector for FileTailCollector {
+    fn name(&self) -> String {
+        self.path.file_name().map_or_else(
+            || "file_tail".to_string(),
+            |name| name.to_string_lossy().to_string(),
+        )
+    }
+
+    fn collect(&mut self, _crash: &CrashInfo) -> Result<Vec<u8>, ProFuzzError> {
+        let mut file = File::open(&self.path)?;
+        let len = file.metadata()?.len();
+        file.seek(SeekFrom::Start(len.saturating_sub(self.max_bytes)))?;
+        let mut buf = vec![];
+        file.read_to_end(&mut buf)?;
+        Ok(buf)
+    }
+}
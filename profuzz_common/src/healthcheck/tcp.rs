@@ -274,6 +274,6 @@ async fn test_tcp_healthcheck() {
 
     for _ in 0..30 {
         assert!(healthcheck.is_ok().await.expect(""));
-        sleep(Duration::from_millis(1000));
+        sleep(Duration::from_secs(1));
     }
 }
@@ -14,6 +14,10 @@
 //! - `Transport`
 //!     - `TcpTransport`: Connects to a TCP server and sends the fuzzing input over TCP.
 //!     - `RawSocketTransport`: Sends the fuzzing input raw on the given interface.
+//! - `CrashCollector`
+//!     - `FileTailCollector`: Stores the last bytes of a file, e.g. a serial log.
+//!     - `CommandCollector`: Stores the stdout of a command.
+//!     - `FileCopyCollector`: Copies a file, e.g. a core dump.
 //!
 //!
 #![deny(missing_docs)]
@@ -31,3 +35,6 @@ pub mod healthcheck;
 
 /// A collection of differed `Mutable` implementations
 pub mod mutable;
+
+/// A collection of differed `CrashCollector` implementations
+pub mod collector;
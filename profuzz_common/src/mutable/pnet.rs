@@ -71,8 +71,108 @@ impl Corpus for EtherMutatorOwned {
     fn show(&self) -> String {
         format!("{}", self.0)
     }
+    fn fields(&self) -> Vec<(String, String)> {
+        let mut ether = self.0.clone();
+        let mut fields = vec![];
+        ether.header_fields(&mut fields);
+        for layer in [Layers::Vlan, Layers::Ipv4, Layers::Udp, Layers::Payload] {
+            match ether.get_layer(&layer) {
+                Some(LayerMut::Vlan(vlan)) => vlan.header_fields(&mut fields),
+                Some(LayerMut::Ipv4(ipv4)) => ipv4.header_fields(&mut fields),
+                Some(LayerMut::Udp(udp)) => udp.header_fields(&mut fields),
+                Some(LayerMut::Payload(payload)) => {
+                    fields.push(("payload".to_string(), hex::encode(&payload.buf)));
+                }
+                _ => (),
+            }
+        }
+        fields
+    }
+    fn copy_field(&mut self, name: &str, other: &Self) -> bool {
+        let mut other = other.0.clone();
+        let layer = match name.split_once('.').map_or(name, |(layer, _)| layer) {
+            "ether" => return self.0.copy_header_field(name, &mut other),
+            "vlan" => Layers::Vlan,
+            "ipv4" => Layers::Ipv4,
+            "udp" => Layers::Udp,
+            "payload" => Layers::Payload,
+            _ => return false,
+        };
+        match (self.0.get_layer(&layer), other.get_layer(&layer)) {
+            (Some(LayerMut::Vlan(vlan)), Some(LayerMut::Vlan(other))) => {
+                vlan.copy_header_field(name, other)
+            }
+            (Some(LayerMut::Ipv4(ipv4)), Some(LayerMut::Ipv4(other))) => {
+                ipv4.copy_header_field(name, other)
+            }
+            (Some(LayerMut::Udp(udp)), Some(LayerMut::Udp(other))) => {
+                udp.copy_header_field(name, other)
+            }
+            (Some(LayerMut::Payload(payload)), Some(LayerMut::Payload(other))) => {
+                payload.buf.clone_from(&other.buf);
+                true
+            }
+            _ => false,
+        }
+    }
 }
 
+/// Access to the header fields of a layer which are mutated, named `<layer>.<field>`. Fields
+/// which are recomputed by `build`, like lengths, checksums or the type of the next layer, are
+/// left out.
+trait HeaderFields {
+    fn header_fields(&mut self, fields: &mut Vec<(String, String)>);
+    fn copy_header_field(&mut self, name: &str, other: &mut Self) -> bool;
+}
+
+macro_rules! header_fields {
+    ($layer:ty, $prefix:literal, $($field:literal: $get:ident, $set:ident, $format:literal);* $(;)?) => {
+        impl HeaderFields for $layer {
+            fn header_fields(&mut self, fields: &mut Vec<(String, String)>) {
+                if let Some(packet) = self.modify() {
+                    $(fields.push((
+                        concat!($prefix, ".", $field).to_string(),
+                        format!($format, packet.$get()),
+                    ));)*
+                }
+            }
+            fn copy_header_field(&mut self, name: &str, other: &mut Self) -> bool {
+                let (Some(mut packet), Some(other)) = (self.modify(), other.modify()) else {
+                    return false;
+                };
+                match name {
+                    $(concat!($prefix, ".", $field) => packet.$set(other.$get()),)*
+                    _ => return false,
+                }
+                true
+            }
+        }
+    };
+}
+
+header_fields!(EtherMut, "ether",
+    "destination": get_destination, set_destination, "{}";
+    "source": get_source, set_source, "{}";
+);
+header_fields!(VlanMut, "vlan",
+    "identifier": get_vlan_identifier, set_vlan_identifier, "{}";
+    "priority_code_point": get_priority_code_point, set_priority_code_point, "{:?}";
+    "drop_eligible_indicator": get_drop_eligible_indicator, set_drop_eligible_indicator, "{}";
+);
+header_fields!(Ipv4Mut, "ipv4",
+    "version": get_version, set_version, "{}";
+    "header_length": get_header_length, set_header_length, "{}";
+    "dscp": get_dscp, set_dscp, "{}";
+    "ecn": get_ecn, set_ecn, "{}";
+    "identification": get_identification, set_identification, "{}";
+    "source": get_source, set_source, "{}";
+    "destination": get_destination, set_destination, "{}";
+);
+header_fields!(UdpMut, "udp",
+    "source": get_source, set_source, "{}";
+    "destination": get_destination, set_destination, "{}";
+);
+
 impl Mutable for EtherMutatorOwned {
     fn mutate<R: rand::Rng>(&mut self, mutator: &mut Mutator<R>) {
         let mut constraint = None;
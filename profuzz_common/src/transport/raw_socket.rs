@@ -3,7 +3,7 @@ use pnet::datalink::Config;
 use pnet::datalink::{self, DataLinkSender};
 use profuzz_core::error::ProFuzzError;
 
-use profuzz_core::traits::Transport;
+use profuzz_core::traits::{Transport, TransportMetadata};
 
 /// Default transport layer for sending directly to an socket.
 /// ! This transport layer requires root permissions when executing!
@@ -29,6 +29,12 @@ impl Transport for RawSocketTransport {
         format!("raw_socket ({})", self.iface)
     }
 
+    fn metadata(&self) -> TransportMetadata {
+        TransportMetadata::RawEthernet {
+            interface: self.iface.clone(),
+        }
+    }
+
     async fn connect(&mut self) -> Result<(), ProFuzzError> {
         if self.socket.is_some() {
             return Ok(());
@@ -6,7 +6,7 @@ use tokio::io::AsyncWriteExt;
 use tokio::net::{TcpStream, ToSocketAddrs};
 use tokio::time::timeout;
 
-use profuzz_core::traits::Transport;
+use profuzz_core::traits::{Transport, TransportMetadata};
 
 /// Tcp Transporter config
 #[derive(Default, Clone)]
@@ -43,6 +43,15 @@ impl<T: ToSocketAddrs + Display> Transport for TcpTransport<T> {
         format!("tcp_client ({})", self.addr)
     }
 
+    fn metadata(&self) -> TransportMetadata {
+        TransportMetadata::Tcp {
+            address: self.addr.to_string(),
+            read_timeout: self.config.read_timeout,
+            write_timeout: self.config.write_timeout,
+            send_after_connected: self.send_after_connected.clone().unwrap_or_default(),
+        }
+    }
+
     async fn connect(&mut self) -> Result<(), ProFuzzError> {
         let stream = TcpStream::connect(&self.addr).await?;
         let _ = stream.set_nodelay(true);
@@ -1,12 +1,25 @@
 use crate::error::ProFuzzError;
+use crate::fuzz::config::{Budget, FuzzConfig, HistoryLimit};
 use crate::fuzz::engine::FuzzEngine;
 use crate::log::Logger;
 use crate::output::Output;
-use crate::traits::{Corpus, HealthCheck, Mutable, ResetHandler, Transport};
-use crate::triage::dynamic::DynamicTriage;
+use crate::traits::{
+    Corpus, CrashCollector, FuzzObserver, HealthCheck, Mutable, ResetHandler, Transport,
+};
+use crate::triage::dedup::{DEFAULT_THRESHOLD, dedup_from_output_dir};
+use crate::triage::dynamic::{DynamicTriage, RegressionStatus, TriageMode, TriageSelection};
+use crate::triage::export::{ExportFormat, export_from_output_dir};
+use crate::triage::minimize::Minimizer;
+use crate::triage::poc::{PocLanguage, poc_from_output_dir};
+use crate::triage::report::{ReportFormat, report_from_output_dir};
+use crate::types::Crash;
+use chrono::SecondsFormat;
+use clap::Args;
 use clap::Parser;
 use clap::Subcommand;
 use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
 
 #[derive(Debug, Parser)]
 struct ProFuzzCliArgs {
@@ -20,27 +33,198 @@ struct ProFuzzCliArgs {
 #[derive(Debug, Subcommand)]
 enum ProFuzzCliCommands {
     /// Triage found crashes to identify the potential root cause
-    Triage {
-        /// output directory for fuzzer findings
-        #[arg(long, short)]
-        out_dir: PathBuf,
-    },
+    Triage(TriageArgs),
+    /// Shrinks the reproducer of a crash while it still crashes the target
+    Minimize(MinimizeArgs),
+    /// Replays the reproducers of all crashes to check which ones are fixed
+    Regress(RegressArgs),
+    /// Clusters the crashes which are likely caused by the same bug, without the target
+    Dedup(DedupArgs),
+    /// Renders a Markdown or HTML report of the crashes
+    Report(ReportArgs),
+    /// Exports the crashes and timeouts as SARIF or `JUnit` XML
+    Export(ExportArgs),
+    /// Generates a standalone Rust or Python proof of concept of a crash
+    Poc(PocArgs),
     // Starts the fuzzing loop
-    Fuzz {
-        /// input directory with test cases
-        // (or '-' to resume, also see PROFUZZ_AUTORESUME)
-        #[arg(long, short)]
-        in_dir: PathBuf,
-        /// output directory for fuzzer findings
-        #[arg(long, short)]
-        out_dir: PathBuf,
-        /// Displays the profuzz UI
-        #[arg(long, default_value_t = false)]
-        hide_ui: bool,
-        /// If output directory is not empty auto resume the session
-        #[arg(long, default_value_t = false)]
-        auto_resume: bool,
-    },
+    Fuzz(FuzzArgs),
+}
+
+#[derive(Debug, Args)]
+#[allow(clippy::struct_excessive_bools)]
+struct TriageArgs {
+    /// output directory for fuzzer findings
+    #[arg(long, short)]
+    out_dir: PathBuf,
+    /// Triages all crashes without asking
+    #[arg(long, default_value_t = false, conflicts_with_all = ["crash", "list"])]
+    all: bool,
+    /// Only triages the crash with the given id
+    #[arg(long, conflicts_with = "list")]
+    crash: Option<usize>,
+    /// Lists all stored crashes
+    #[arg(long, default_value_t = false)]
+    list: bool,
+    /// Shrinks the history to a minimal crashing sequence instead of testing every buffer
+    /// on its own. The target is reset between every trial
+    #[arg(long, default_value_t = false)]
+    ddmin: bool,
+    /// Reverts the fields differing from the closest non-crashing buffer on the target to
+    /// identify which fields and values are necessary for the crash
+    #[arg(long, default_value_t = false, conflicts_with = "ddmin")]
+    isolate: bool,
+    /// Replays every reproducer the given number of times, resetting the target in between,
+    /// and labels the crash as reliable, flaky or not reproducible
+    #[arg(long)]
+    repeat: Option<usize>,
+}
+
+#[derive(Debug, Args)]
+struct RegressArgs {
+    /// output directory for fuzzer findings
+    #[arg(long, short)]
+    out_dir: PathBuf,
+}
+
+#[derive(Debug, Args)]
+struct DedupArgs {
+    /// output directory for fuzzer findings
+    #[arg(long, short)]
+    out_dir: PathBuf,
+    /// Minimal similarity of the structured representation of two crashes in the same cluster
+    #[arg(long, default_value_t = DEFAULT_THRESHOLD)]
+    threshold: f64,
+}
+
+#[derive(Debug, Args)]
+struct ReportArgs {
+    /// output directory for fuzzer findings
+    #[arg(long, short)]
+    out_dir: PathBuf,
+    /// Format of the reports
+    #[arg(long, value_enum, default_value_t = ReportFormat::Markdown)]
+    format: ReportFormat,
+    /// Only renders the report of the crash with the given id
+    #[arg(long)]
+    crash: Option<usize>,
+}
+
+#[derive(Debug, Args)]
+struct ExportArgs {
+    /// output directory for fuzzer findings
+    #[arg(long, short)]
+    out_dir: PathBuf,
+    /// Format of the export
+    #[arg(long, value_enum)]
+    format: ExportFormat,
+    /// File the export is written to, defaults to `findings.sarif` or `junit.xml` in the
+    /// output directory
+    #[arg(long)]
+    file: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+struct PocArgs {
+    /// output directory for fuzzer findings
+    #[arg(long, short)]
+    out_dir: PathBuf,
+    /// Id of the crash
+    #[arg(long)]
+    crash: usize,
+    /// Language of the proof of concept
+    #[arg(long, value_enum, default_value_t = PocLanguage::Python)]
+    language: PocLanguage,
+}
+
+#[derive(Debug, Args)]
+struct MinimizeArgs {
+    /// output directory for fuzzer findings
+    #[arg(long, short)]
+    out_dir: PathBuf,
+    /// Id of the crash to minimize
+    #[arg(long)]
+    crash: usize,
+    /// Maximal number of times the target is tested
+    #[arg(long, default_value_t = 200)]
+    max_trials: usize,
+}
+
+#[derive(Debug, Args)]
+struct FuzzArgs {
+    /// input directory with test cases
+    // (or '-' to resume, also see PROFUZZ_AUTORESUME)
+    #[arg(long, short)]
+    in_dir: PathBuf,
+    /// output directory for fuzzer findings
+    #[arg(long, short)]
+    out_dir: PathBuf,
+    /// Displays the profuzz UI
+    #[arg(long, default_value_t = false)]
+    hide_ui: bool,
+    /// If output directory is not empty auto resume the session
+    #[arg(long, default_value_t = false)]
+    auto_resume: bool,
+    /// Seed for the mutations, a random seed is used if not set. When resuming the stored
+    /// seed is used
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Stops the fuzzer after the given number of seconds
+    #[arg(long)]
+    max_time: Option<u64>,
+    /// Stops the fuzzer after the given number of executions
+    #[arg(long)]
+    max_execs: Option<u64>,
+    /// Stops the fuzzer as soon as the first crash was detected
+    #[arg(long, default_value_t = false)]
+    stop_on_first_crash: bool,
+    /// Shell command which is executed every time a crash was stored. The crash is passed
+    /// with the environment variables `PROFUZZ_CRASH_FILE`, `PROFUZZ_CRASH_BUFFER`,
+    /// `PROFUZZ_CRASH_ID` and `PROFUZZ_OUTPUT_DIR`
+    #[arg(long)]
+    on_crash: Option<String>,
+    /// Number of seconds after which the on-crash command is killed
+    #[arg(long, default_value_t = 60)]
+    on_crash_timeout: u64,
+    /// Maximal number of sent buffers stored with every crash
+    #[arg(long, default_value_t = 20_000)]
+    history_entries: usize,
+    /// Maximal number of MiB used to store the sent buffers of a worker
+    #[arg(
+        long,
+        default_value_t = 64,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..=1024 * 1024)
+    )]
+    history_mib: usize,
+    /// How often each queue element is used as root for a new round of stacked mutations
+    #[arg(long, default_value_t = 50)]
+    rounds_per_seed: usize,
+    /// Number of mutations stacked on top of each other in a round
+    #[arg(long, default_value_t = 1000)]
+    mutations_per_round: usize,
+    /// Maximal number of seconds between two health checks in case sending does not fail
+    #[arg(long, default_value_t = 5)]
+    healthcheck_interval: u64,
+    /// Maximal number of executions of a worker between two health checks in case sending does
+    /// not fail
+    #[arg(long, default_value_t = 20_000)]
+    healthcheck_executions: usize,
+}
+
+/// The exit status of the CLI application, so `profuzz` can be used to gate a CI pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// The command finished without any findings
+    NoFindings = 0,
+    /// At least one crash was found in the current session
+    CrashFound = 1,
+    /// The command could not be started or stopped with an error
+    SetupFailure = 2,
+}
+
+impl From<ExitStatus> for ExitCode {
+    fn from(status: ExitStatus) -> Self {
+        ExitCode::from(status as u8)
+    }
 }
 
 /// A helper struct to setup the CLI application or to start the fuzzer or triaging with fewer
@@ -49,6 +233,10 @@ pub struct ProFuzzBuilder<T: Transport, H: HealthCheck, R: ResetHandler> {
     transport: T,
     healthcheck: H,
     resethandler: R,
+    /// Additional identical targets which are fuzzed in parallel
+    workers: Vec<(T, H, R)>,
+    observers: Vec<Box<dyn FuzzObserver>>,
+    collectors: Vec<Box<dyn CrashCollector>>,
 }
 
 impl<T: Transport, H: HealthCheck, R: ResetHandler> ProFuzzBuilder<T, H, R> {
@@ -58,38 +246,306 @@ impl<T: Transport, H: HealthCheck, R: ResetHandler> ProFuzzBuilder<T, H, R> {
             transport,
             healthcheck,
             resethandler,
+            workers: vec![],
+            observers: vec![],
+            collectors: vec![],
         }
     }
+
+    /// Adds another identical target which is fuzzed in parallel. All targets share the same
+    /// queue, crashes and stats. Triaging is always done with the first target.
+    #[must_use]
+    pub fn add_worker(mut self, transport: T, healthcheck: H, resethandler: R) -> Self {
+        self.workers.push((transport, healthcheck, resethandler));
+        self
+    }
+
+    /// Registers an observer which is notified about new responses, crashes, resets and
+    /// finished cycles while fuzzing.
+    #[must_use]
+    pub fn add_observer<O: FuzzObserver + 'static>(mut self, observer: O) -> Self {
+        self.observers.push(Box::new(observer));
+        self
+    }
+
+    /// Registers a collector which stores artifacts like serial logs next to every crash before
+    /// the target is reset.
+    #[must_use]
+    pub fn add_collector<C: CrashCollector + 'static>(mut self, collector: C) -> Self {
+        self.collectors.push(Box::new(collector));
+        self
+    }
 }
 
 impl<T: Transport, H: HealthCheck, R: ResetHandler> ProFuzzBuilder<T, H, R> {
-    /// Starts the `ProFuzzer` as a CLI application
-    /// # Errors
-    pub async fn start_cli<M>(self) -> Result<(), ProFuzzError>
+    /// Starts the `ProFuzzer` as a CLI application. Errors are printed and the returned exit
+    /// code reports if crashes were found, see `ExitStatus`.
+    pub async fn start_cli<M>(self) -> ExitCode
+    where
+        M: Corpus + Mutable + Clone,
+    {
+        match self.run_cli::<M>().await {
+            Ok(status) => status.into(),
+            Err(err) => {
+                eprintln!("{err}");
+                ExitStatus::SetupFailure.into()
+            }
+        }
+    }
+
+    async fn run_cli<M>(self) -> Result<ExitStatus, ProFuzzError>
     where
         M: Corpus + Mutable + Clone,
     {
         let args = ProFuzzCliArgs::parse();
         let logger = Logger::init(args.verbose);
         match args.command {
-            ProFuzzCliCommands::Triage { out_dir } => {
-                let output = Output::init(out_dir, true)?;
-                let mut triager =
-                    DynamicTriage::new(self.transport, self.healthcheck, self.resethandler);
-                triager.triage_from_output_dir::<M>(&output).await?;
-                Ok(())
-            }
-            ProFuzzCliCommands::Fuzz {
-                in_dir,
-                out_dir,
-                hide_ui,
-                auto_resume,
-            } => {
-                let output = Output::init(out_dir, auto_resume)?;
-                let mut fuzzengine: FuzzEngine<M, _, _, _> =
-                    FuzzEngine::new(self.transport, self.healthcheck, self.resethandler, output);
-                fuzzengine.fuzz(!hide_ui, &in_dir, Some(logger)).await
-            }
+            ProFuzzCliCommands::Triage(args) => self.triage::<M>(args).await,
+            ProFuzzCliCommands::Minimize(args) => self.minimize::<M>(args).await,
+            ProFuzzCliCommands::Regress(args) => self.regress(args).await,
+            ProFuzzCliCommands::Dedup(args) => Self::dedup::<M>(args),
+            ProFuzzCliCommands::Report(args) => Self::report::<M>(args),
+            ProFuzzCliCommands::Export(args) => Self::export(args),
+            ProFuzzCliCommands::Poc(args) => Self::poc(args),
+            ProFuzzCliCommands::Fuzz(args) => self.fuzz::<M>(args, logger).await,
+        }
+    }
+
+    async fn triage<M>(self, args: TriageArgs) -> Result<ExitStatus, ProFuzzError>
+    where
+        M: Corpus + Mutable + Clone,
+    {
+        let output = Output::init(args.out_dir, true)?;
+        if args.list {
+            list_crashes(&output)?;
+            return Ok(ExitStatus::NoFindings);
+        }
+        let selection = match args.crash {
+            Some(id) => TriageSelection::Crash(id),
+            None if args.all => TriageSelection::All,
+            None => TriageSelection::Interactive,
+        };
+        let mut triager = DynamicTriage::new(self.transport, self.healthcheck, self.resethandler);
+        if args.ddmin {
+            triager.set_mode(TriageMode::Ddmin);
+        }
+        triager.set_isolate(args.isolate);
+        triager.set_repeat(args.repeat);
+        let results = triager
+            .triage_from_output_dir::<M>(&output, selection)
+            .await?;
+        if selection != TriageSelection::Interactive {
+            let content = serde_json::to_string_pretty(&results)?;
+            std::fs::write(output.get_path().join("triage.json"), &content)?;
+            println!("{content}");
+        }
+        Ok(ExitStatus::NoFindings)
+    }
+
+    async fn minimize<M>(self, args: MinimizeArgs) -> Result<ExitStatus, ProFuzzError>
+    where
+        M: Corpus + Mutable + Clone,
+    {
+        let output = Output::init(args.out_dir, true)?;
+        let mut minimizer = Minimizer::new(self.transport, self.healthcheck, self.resethandler);
+        minimizer.set_max_trials(args.max_trials);
+        let result = minimizer
+            .minimize_from_output_dir::<M>(&output, args.crash)
+            .await?;
+        println!("{}", serde_json::to_string_pretty(&result)?);
+        Ok(ExitStatus::NoFindings)
+    }
+
+    async fn regress(self, args: RegressArgs) -> Result<ExitStatus, ProFuzzError> {
+        let output = Output::init(args.out_dir, true)?;
+        let mut triager = DynamicTriage::new(self.transport, self.healthcheck, self.resethandler);
+        let results = triager.regress_from_output_dir(&output).await?;
+        let content = serde_json::to_string_pretty(&results)?;
+        Output::write_atomic(&output.get_path().join("regress.json"), content.as_bytes())?;
+
+        println!("{:>5}  {:<32}  {:>7}  result", "id", "target", "buffers");
+        for result in &results {
+            let status = match result.status {
+                RegressionStatus::Passed => "pass",
+                RegressionStatus::Failed => "FAIL",
+                RegressionStatus::Skipped => "skipped (no reproducer)",
+            };
+            println!(
+                "{:>5}  {:<32}  {:>7}  {status}",
+                result.crash_id, result.target, result.buffers
+            );
+        }
+        let failed = results
+            .iter()
+            .filter(|r| r.status == RegressionStatus::Failed)
+            .count();
+        println!(
+            "{failed}/{} reproducers still crash the target.",
+            results.len()
+        );
+        println!(
+            "Report stored in {}",
+            output.get_path().join("regress.json").display()
+        );
+        if failed > 0 {
+            Ok(ExitStatus::CrashFound)
+        } else {
+            Ok(ExitStatus::NoFindings)
+        }
+    }
+
+    fn dedup<M: Corpus>(args: DedupArgs) -> Result<ExitStatus, ProFuzzError> {
+        let output = Output::init(args.out_dir, true)?;
+        let clusters = dedup_from_output_dir::<M>(&output, args.threshold)?;
+        println!(
+            "{:>7}  {:>14}  {:>7}  failure",
+            "cluster", "representative", "crashes"
+        );
+        for cluster in &clusters {
+            println!(
+                "{:>7}  {:>14}  {:>7}  {}",
+                cluster.id,
+                cluster.representative,
+                cluster.crashes.len(),
+                cluster.failure.as_deref().unwrap_or("unknown")
+            );
+        }
+        println!(
+            "{} crashes in {} clusters, stored in {}",
+            clusters.iter().map(|c| c.crashes.len()).sum::<usize>(),
+            clusters.len(),
+            output.get_clusters_file().display()
+        );
+        Ok(ExitStatus::NoFindings)
+    }
+
+    fn report<M: Corpus>(args: ReportArgs) -> Result<ExitStatus, ProFuzzError> {
+        let output = Output::init(args.out_dir, true)?;
+        for path in report_from_output_dir::<M>(&output, args.format, args.crash)? {
+            println!("{}", path.display());
+        }
+        Ok(ExitStatus::NoFindings)
+    }
+
+    fn export(args: ExportArgs) -> Result<ExitStatus, ProFuzzError> {
+        let output = Output::init(args.out_dir, true)?;
+        let content = export_from_output_dir(&output, args.format)?;
+        let file = args
+            .file
+            .unwrap_or_else(|| output.get_path().join(args.format.file_name()));
+        Output::write_atomic(&file, content.as_bytes())?;
+        println!("{}", file.display());
+        Ok(ExitStatus::NoFindings)
+    }
+
+    fn poc(args: PocArgs) -> Result<ExitStatus, ProFuzzError> {
+        let output = Output::init(args.out_dir, true)?;
+        let path = poc_from_output_dir(&output, args.crash, args.language)?;
+        println!("{}", path.display());
+        Ok(ExitStatus::NoFindings)
+    }
+
+    async fn fuzz<M>(self, args: FuzzArgs, logger: Logger) -> Result<ExitStatus, ProFuzzError>
+    where
+        M: Corpus + Mutable + Clone,
+    {
+        let config = FuzzConfig {
+            in_dir: args.in_dir,
+            out_dir: args.out_dir,
+            auto_resume: args.auto_resume,
+            seed: args.seed,
+            enable_ui: !args.hide_ui,
+            handle_signals: true,
+            healthcheck_interval: Duration::from_secs(args.healthcheck_interval),
+            healthcheck_executions: args.healthcheck_executions,
+            rounds_per_seed: args.rounds_per_seed,
+            mutations_per_round: args.mutations_per_round,
+            budget: Budget {
+                max_time: args.max_time.map(Duration::from_secs),
+                max_execs: args.max_execs,
+                stop_on_first_crash: args.stop_on_first_crash,
+            },
+            on_crash: args.on_crash,
+            on_crash_timeout: Duration::from_secs(args.on_crash_timeout),
+            history: HistoryLimit {
+                max_entries: Some(args.history_entries),
+                max_bytes: Some(args.history_mib.saturating_mul(1024 * 1024)),
+            },
+        };
+        let mut fuzzengine: FuzzEngine<M, _, _, _> =
+            FuzzEngine::new(config, self.transport, self.healthcheck, self.resethandler)?;
+        for (transport, healthcheck, resethandler) in self.workers {
+            fuzzengine.add_worker(transport, healthcheck, resethandler);
+        }
+        for observer in self.observers {
+            fuzzengine.add_boxed_observer(observer);
+        }
+        for collector in self.collectors {
+            fuzzengine.add_boxed_collector(collector);
         }
+        fuzzengine.set_logger(logger);
+        let summary = fuzzengine.fuzz().await?;
+        tracing::info!(
+            "Fuzzer stopped ({}) after {} executions with {} new crashes.",
+            summary.stop_reason,
+            summary.executions,
+            summary.new_crashes
+        );
+        if summary.new_crashes > 0 {
+            Ok(ExitStatus::CrashFound)
+        } else {
+            Ok(ExitStatus::NoFindings)
+        }
+    }
+}
+
+/// Prints an overview of all crashes stored in the output directory.
+fn list_crashes(output: &Output) -> Result<(), ProFuzzError> {
+    println!(
+        "{:>5}  {:<32}  {:<27}  {:>8}  {:<7}  verdict",
+        "id", "target", "detected at", "history", "triaged"
+    );
+    for id in output.get_crash_ids()? {
+        let crash = Crash::load_meta(output, id)?;
+        let detected_at = crash.detected_at.map_or_else(
+            || "unknown".to_string(),
+            |time| time.to_rfc3339_opts(SecondsFormat::Micros, true),
+        );
+        let triaged = output.get_crash_dir(crash.id).join("triage.json").is_file();
+        let verdict = crash.reproducibility.map_or_else(
+            || "-".to_string(),
+            |r| format!("{:?} ({}/{})", r.verdict, r.reproduced, r.attempts),
+        );
+        println!(
+            "{:>5}  {:<32}  {:<27}  {:>8}  {:<7}  {}",
+            crash.id,
+            crash.target,
+            detected_at,
+            Crash::history_len(output, id)?,
+            if triaged { "yes" } else { "no" },
+            verdict
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history_mib(value: &str) -> Option<usize> {
+        let args = ["profuzz", "fuzz", "--in-dir", "in", "--out-dir", "out"];
+        let args = ProFuzzCliArgs::try_parse_from(args.into_iter().chain(["--history-mib", value]));
+        match args.ok()?.command {
+            ProFuzzCliCommands::Fuzz(args) => Some(args.history_mib),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn limits_the_history_size() {
+        assert_eq!(history_mib("64"), Some(64));
+        assert_eq!(history_mib("0"), None);
+        assert_eq!(history_mib("18446744073709551615"), None);
     }
 }
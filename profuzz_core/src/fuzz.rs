@@ -1,8 +1,15 @@
 /// contains the fuzzing engine which starts the main fuzzing loop and the TUI
 pub mod engine;
 
+/// Contains the configuration of the fuzzing engine used in headless mode.
+pub mod config;
+
+/// Contains the `FuzzControl` handle to control a running engine and the events it emits.
+pub mod control;
+
 /// Contains all statistical data collected by the fuzzing engine. If used with a TUI they will be
 /// displayed there.
 pub mod stats;
 
+mod history;
 mod ui;
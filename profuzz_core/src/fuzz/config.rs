@@ -0,0 +1,92 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Configuration of a fuzzing campaign, used to start the `FuzzEngine` in headless mode. When
+/// using the CLI this is created from the command line arguments.
+#[derive(Debug, Clone)]
+pub struct FuzzConfig {
+    /// Input directory with the initial corpus files
+    pub in_dir: PathBuf,
+    /// Output directory for the fuzzer findings
+    pub out_dir: PathBuf,
+    /// Resume the session in case the output directory already exists
+    pub auto_resume: bool,
+    /// Seed for the mutations, a random seed is used if not set
+    pub seed: Option<u64>,
+    /// Displays the TUI while fuzzing
+    pub enable_ui: bool,
+    /// Stops the fuzzer cleanly on SIGINT and SIGTERM and exits on a second signal. Keep this
+    /// disabled when the application embedding the engine handles the signals itself, e.g. by
+    /// calling `FuzzControl::stop`.
+    pub handle_signals: bool,
+    /// Maximum time between two health checks in case sending does not fail
+    pub healthcheck_interval: Duration,
+    /// Maximum number of executions of a worker between two health checks in case sending does
+    /// not fail
+    pub healthcheck_executions: usize,
+    /// How often each queue element is used as root for a new round of stacked mutations
+    pub rounds_per_seed: usize,
+    /// Number of mutations stacked on top of each other in a round, starting from the queue
+    /// element
+    pub mutations_per_round: usize,
+    /// Limits after which the fuzzer stops
+    pub budget: Budget,
+    /// Shell command which is executed every time a crash was stored, before the target is reset
+    pub on_crash: Option<String>,
+    /// Time after which the `on_crash` command is killed
+    pub on_crash_timeout: Duration,
+    /// Limits of the history of sent buffers stored with every crash
+    pub history: HistoryLimit,
+}
+
+impl Default for FuzzConfig {
+    fn default() -> Self {
+        Self {
+            in_dir: PathBuf::default(),
+            out_dir: PathBuf::default(),
+            auto_resume: false,
+            seed: None,
+            enable_ui: false,
+            handle_signals: false,
+            healthcheck_interval: Duration::from_secs(5),
+            healthcheck_executions: 20_000,
+            rounds_per_seed: 50,
+            mutations_per_round: 1000,
+            budget: Budget::default(),
+            on_crash: None,
+            on_crash_timeout: Duration::from_mins(1),
+            history: HistoryLimit::default(),
+        }
+    }
+}
+
+/// Limits after which the fuzzing loop is stopped. By default the fuzzer runs until it is
+/// stopped by the user.
+#[derive(Debug, Clone, Default)]
+pub struct Budget {
+    /// Stops after the fuzzer ran for the given time
+    pub max_time: Option<Duration>,
+    /// Stops after the given number of executions
+    pub max_execs: Option<u64>,
+    /// Stops as soon as the first crash was detected
+    pub stop_on_first_crash: bool,
+}
+
+/// Limits of the history of sent buffers which is stored with every crash. The oldest buffers
+/// are dropped as soon as one of the limits is reached.
+#[derive(Debug, Clone, Copy)]
+pub struct HistoryLimit {
+    /// Maximal number of stored buffers
+    pub max_entries: Option<usize>,
+    /// Maximal number of bytes used to store the buffers
+    pub max_bytes: Option<usize>,
+}
+
+impl Default for HistoryLimit {
+    fn default() -> Self {
+        Self {
+            max_entries: Some(20_000),
+            max_bytes: Some(64 * 1024 * 1024),
+        }
+    }
+}
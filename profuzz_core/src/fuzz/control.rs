@@ -0,0 +1,171 @@
+use crate::fuzz::engine::StopReason;
+use crate::fuzz::stats::{Stats, StatsType};
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use tokio::sync::broadcast;
+
+/// Number of events which are buffered for slow subscribers before they start lagging
+const EVENT_CAPACITY: usize = 1024;
+
+/// Events emitted by the fuzzing engine. A host application can subscribe to them with
+/// `FuzzControl::subscribe`.
+#[derive(Debug, Clone)]
+pub enum FuzzEvent {
+    /// The fuzzing loop was started
+    Started,
+    /// A corpus produced a unique response and was added to the queue
+    NewResponse {
+        /// Worker which found the response
+        worker: usize,
+        /// The corpus which produced the response
+        corpus: Vec<u8>,
+    },
+    /// The health check failed and a crash was stored
+    Crash(CrashInfo),
+    /// The `ResetHandler` of a worker was triggered
+    Reset {
+        /// Worker whose target was reset
+        worker: usize,
+    },
+    /// A health check was performed
+    HealthCheck {
+        /// Worker which performed the health check
+        worker: usize,
+        /// Result of the health check
+        healthy: bool,
+        /// Wall-clock time of the health check
+        checked_at: DateTime<Utc>,
+    },
+    /// All queue elements were used as mutation root
+    CycleDone {
+        /// Total number of cycles done
+        cycles: usize,
+    },
+    /// The fuzzing loop was paused
+    Paused,
+    /// The fuzzing loop was resumed
+    Resumed,
+    /// The fuzzing loop stopped
+    Stopped {
+        /// Why the fuzzer stopped
+        reason: StopReason,
+    },
+}
+
+/// Information about a stored crash.
+#[derive(Debug, Clone)]
+pub struct CrashInfo {
+    /// Id of the crash in the output directory
+    pub id: usize,
+    /// Worker whose target crashed
+    pub worker: usize,
+    /// Title of the transport of the crashed target
+    pub target: String,
+    /// The last buffer sent before the crash was detected
+    pub buffer: Vec<u8>,
+    /// True if the crash does not belong to the cluster of a previous crash
+    pub unique: bool,
+    /// Wall-clock time of the failed health check which detected the crash
+    pub detected_at: DateTime<Utc>,
+}
+
+/// A cloneable handle to control a running `FuzzEngine`, e.g. when it is embedded into a host
+/// application which orchestrates multiple campaigns. It can be obtained with
+/// `FuzzEngine::control`.
+#[derive(Clone)]
+pub struct FuzzControl {
+    stats: StatsType,
+    paused: Arc<AtomicBool>,
+    /// Set by `stop`, so a stop requested before `FuzzEngine::fuzz` started is not lost
+    stop_requested: Arc<AtomicBool>,
+    /// Incremented for every requested health check, so every worker can detect a new request
+    healthcheck_requests: Arc<AtomicU64>,
+    events: broadcast::Sender<FuzzEvent>,
+}
+
+impl FuzzControl {
+    pub(crate) fn new(stats: StatsType) -> Self {
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+        Self {
+            stats,
+            paused: Arc::default(),
+            stop_requested: Arc::default(),
+            healthcheck_requests: Arc::default(),
+            events,
+        }
+    }
+
+    /// Pauses the fuzzing loop. The workers finish their current execution and then wait until
+    /// the engine is resumed or stopped.
+    pub fn pause(&self) {
+        if !self.paused.swap(true, Ordering::SeqCst) {
+            tracing::info!("Fuzzer paused.");
+            self.emit(FuzzEvent::Paused);
+        }
+    }
+
+    /// Resumes a paused fuzzing loop.
+    pub fn resume(&self) {
+        if self.paused.swap(false, Ordering::SeqCst) {
+            tracing::info!("Fuzzer resumed.");
+            self.emit(FuzzEvent::Resumed);
+        }
+    }
+
+    /// Stops the fuzzing loop. The state is written to the output directory and
+    /// `FuzzEngine::fuzz` returns. If the engine is not started yet, the next call of
+    /// `FuzzEngine::fuzz` returns right after the initial health check.
+    pub fn stop(&self) {
+        self.stop_requested.store(true, Ordering::SeqCst);
+        if let Ok(mut stats) = self.stats.write() {
+            stats.running = false;
+        }
+    }
+
+    /// Requests an immediate health check from every worker.
+    pub fn request_healthcheck(&self) {
+        self.healthcheck_requests.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Subscribes to the events of the engine.
+    #[must_use]
+    pub fn subscribe(&self) -> broadcast::Receiver<FuzzEvent> {
+        self.events.subscribe()
+    }
+
+    /// Returns true if the fuzzing loop is paused.
+    #[must_use]
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Returns true if the fuzzing loop is running.
+    #[must_use]
+    pub fn is_running(&self) -> bool {
+        self.stats.read().is_ok_and(|stats| stats.running)
+    }
+
+    /// Returns a snapshot of the current stats.
+    #[must_use]
+    pub fn stats(&self) -> Stats {
+        self.stats
+            .read()
+            .map(|stats| stats.clone())
+            .unwrap_or_default()
+    }
+
+    /// Returns true if `stop` was called since the last call and resets the request.
+    pub(crate) fn take_stop_request(&self) -> bool {
+        self.stop_requested.swap(false, Ordering::SeqCst)
+    }
+
+    pub(crate) fn healthcheck_requests(&self) -> u64 {
+        self.healthcheck_requests.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn emit(&self, event: FuzzEvent) {
+        // sending only fails if there are no subscribers
+        let _ = self.events.send(event);
+    }
+}
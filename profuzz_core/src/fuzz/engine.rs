@@ -1,26 +1,43 @@
-use crate::error::ProFuzzError;
-use crate::fuzz::stats::{ExecsPerSecond, SerializableInstant, StatsType};
+use crate::error::{ProFuzzError, ProFuzzResult};
+use crate::fuzz::config::FuzzConfig;
+use crate::fuzz::control::{CrashInfo, FuzzControl, FuzzEvent};
+use crate::fuzz::history::SendHistory;
+use crate::fuzz::stats::{ExecsPerSecond, SerializableInstant, Stats, StatsType};
 use crate::fuzz::ui::show_ui;
 use crate::log::Logger;
 use crate::mutator::Mutator;
 use crate::output::Output;
-use crate::traits::{Corpus, HealthCheck, Mutable, ResetHandler, Transport};
-use crate::types::Crash;
+use crate::traits::{
+    Corpus, CrashCollector, FuzzObserver, HealthCheck, Mutable, ResetHandler, Transport,
+};
+use crate::triage::dedup::{CrashClusters, CrashFeatures, DEFAULT_THRESHOLD, UNHEALTHY};
+use crate::types::{
+    CRASH_BUFFER_FILE, CRASH_META_FILE, Crash, RngState, SavedQueue, SavedQueueEntry, TargetInfo,
+    Timeout,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
 use rand::SeedableRng;
 use rand_chacha::ChaChaRng;
+use serde::Serialize;
 use sha1::{Digest, Sha1};
-use std::collections::{HashSet, VecDeque};
+use std::collections::HashSet;
+use std::fmt::Display;
 use std::fs;
 use std::fs::File;
 use std::io::BufReader;
 use std::io::Read;
-use std::path::PathBuf;
-use std::sync::Arc;
-use std::thread::sleep;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
+use tokio::time::sleep;
 
 /// The main fuzzing engine containing the coordination of the different traits like connecting to
 /// the target, performing the healthcheck or reseting the target.
+///
+/// The engine can drive multiple identical targets in parallel. Every target is handled by its
+/// own worker with its own `Transport`, `HealthCheck` and `ResetHandler`, while the queue, the
+/// crashes and the stats are shared between all workers.
 pub struct FuzzEngine<M, T, H, R>
 where
     M: Clone + Corpus + Mutable,
@@ -28,21 +45,187 @@ where
     H: HealthCheck,
     R: ResetHandler,
 {
+    workers: Vec<Worker<T, H, R>>,
+    campaign: Campaign<M>,
+    /// True if the seed was given by the user and should not be replaced by the stored one
+    seed_requested: bool,
+    logger: Option<Logger>,
+}
+
+/// Summary of a fuzzing session returned by `FuzzEngine::fuzz`.
+#[derive(Debug, Clone, Serialize)]
+pub struct FuzzSummary {
+    /// Executions performed in this session
+    pub executions: u64,
+    /// Executions performed over all resumed sessions
+    pub total_executions: u64,
+    /// Crashes stored in this session, including crashes of already known clusters
+    pub new_crashes: usize,
+    /// Unique crashes, i.e. crash clusters, found over all resumed sessions
+    pub total_crashes: usize,
+    /// Unique responses found in this session
+    pub new_responses: usize,
+    /// Number of elements in the queue
+    pub corpus_count: usize,
+    /// Cycles completed over all resumed sessions
+    pub cycles_done: usize,
+    /// Runtime of this session
+    pub duration: Duration,
+    /// Why the fuzzer stopped
+    pub stop_reason: StopReason,
+}
+
+/// The reason why the fuzzing loop stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum StopReason {
+    /// Stopped by the user, e.g. by Ctrl-C, a signal or the TUI
+    Stopped,
+    /// The `max_time` of the budget was reached
+    TimeLimit,
+    /// The `max_execs` of the budget was reached
+    ExecutionLimit,
+    /// A crash was found and `stop_on_first_crash` is enabled
+    FirstCrash,
+}
+
+impl Display for StopReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StopReason::Stopped => write!(f, "stopped by the user"),
+            StopReason::TimeLimit => write!(f, "time limit reached"),
+            StopReason::ExecutionLimit => write!(f, "execution limit reached"),
+            StopReason::FirstCrash => write!(f, "first crash found"),
+        }
+    }
+}
+
+struct QueueElement<M> {
+    corpus: M,
+    /// Hash of the response which added this element, `None` for the initial corpus
+    response_hash: Option<Vec<u8>>,
+    found_at_execution: u64,
+}
+
+/// Interval in which the current state (e.g. the queue) is written to the output directory
+const STATE_FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Number of sent buffers kept in the history after a successful health check
+const HISTORY_KEPT_AFTER_HEALTHCHECK: usize = 5_000;
+
+/// Number of timeouts stored in the output directory, so a hanging target does not fill the disk
+const MAX_STORED_TIMEOUTS: usize = 1000;
+
+/// Everything that is shared between the workers of a campaign.
+struct Campaign<M> {
+    stats: StatsType,
+    output: Output,
+    seed: u64,
+    state: Mutex<CampaignState<M>>,
+    config: FuzzConfig,
+    control: FuzzControl,
+    observers: Mutex<Vec<Box<dyn FuzzObserver>>>,
+    collectors: Arc<Mutex<Vec<Box<dyn CrashCollector>>>>,
+    /// Start of the current session, as the stats are cumulative over all resumed sessions
+    session_started: Instant,
+    execs_at_start: u64,
+    /// Crashes stored in this session. In contrast to `Stats::total_crashes` this includes
+    /// crashes which were added to an existing cluster.
+    new_crashes: AtomicUsize,
+    responses_at_start: usize,
+}
+
+struct CampaignState<M> {
+    queue: Vec<QueueElement<M>>,
+    /// Index of the next queue element which is used as mutation root
+    queue_position: usize,
+    /// Crashes grouped by the bug which likely caused them
+    crash_clusters: CrashClusters,
+    unique_responses: HashSet<Vec<u8>>,
+    /// Current word position of the RNG of each worker
+    rng_positions: Vec<u128>,
+    last_state_flush: Instant,
+    stop_reason: Option<StopReason>,
+    /// Id of the next stored crash, read once from the output directory
+    next_crash_id: usize,
+    /// Id of the next stored timeout, read once from the output directory
+    next_timeout_id: usize,
+}
+
+/// A single target driven by the engine.
+struct Worker<T, H, R> {
+    id: usize,
     transport: T,
     healthcheck: H,
     resethandler: R,
     mutator: Mutator<ChaChaRng>,
-    pub(crate) stats: StatsType,
-    output: Output,
-    queue: VecDeque<QueueElement<M>>,
-    last_send_buffers: VecDeque<Vec<u8>>,
-    unique_crashes: HashSet<Vec<u8>>,
-    unique_responses: HashSet<Vec<u8>>,
+    /// Buffers sent to the target, stored with a crash so it can be reproduced
+    history: SendHistory,
     read_buffer: Vec<u8>,
+    /// Last health check request of the `FuzzControl` handled by this worker
+    healthcheck_requests_seen: u64,
+    /// Wall-clock time of the last successful health check
+    last_healthcheck_at: Option<DateTime<Utc>>,
+    /// Wall-clock time the last buffer was written to the transport, `None` if writing failed
+    written_at: Option<DateTime<Utc>>,
 }
 
-struct QueueElement<M> {
-    corpus: M,
+/// Signal numbers used for the exit code `128 + signal` on a second signal
+const SIGINT: i32 = 2;
+#[cfg(unix)]
+const SIGTERM: i32 = 15;
+
+/// Stops the fuzzing loop when the process receives a SIGINT or SIGTERM, so the state can be
+/// written to the output directory before exiting. This is required in case the TUI is disabled
+/// or the fuzzer runs under a process supervisor. A second signal exits the process immediately,
+/// e.g. when a worker is stuck in a reset or a health check, with the exit code `128 + signal`.
+fn spawn_signal_handler(stats: StatsType) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        #[cfg(unix)]
+        let Ok(mut sigterm) =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+        else {
+            tracing::error!("Could not register the SIGTERM handler.");
+            return;
+        };
+        let mut stopping = false;
+        loop {
+            #[cfg(unix)]
+            let (name, signal) = tokio::select! {
+                _ = tokio::signal::ctrl_c() => ("SIGINT", SIGINT),
+                _ = sigterm.recv() => ("SIGTERM", SIGTERM),
+            };
+            #[cfg(not(unix))]
+            let name = {
+                if tokio::signal::ctrl_c().await.is_err() {
+                    tracing::error!("Could not register the Ctrl-C handler.");
+                    return;
+                }
+                ("Ctrl-C", SIGINT)
+            };
+            if stopping {
+                tracing::warn!("Received {name} again, exiting without saving the state.");
+                // like a shell, report the signal which terminated the process
+                std::process::exit(128 + signal);
+            }
+            tracing::info!(
+                "Received {name}, stopping the fuzzer. Send it again to exit immediately."
+            );
+            if let Ok(mut stats) = stats.write() {
+                stats.running = false;
+            }
+            stopping = true;
+        }
+    })
+}
+
+/// Creates the mutator of a worker. Every worker uses its own stream of the seeded RNG.
+fn worker_mutator(seed: u64, worker: usize, word_pos: Option<u128>) -> Mutator<ChaChaRng> {
+    let mut rng = ChaChaRng::seed_from_u64(seed);
+    rng.set_stream(worker as u64);
+    if let Some(word_pos) = word_pos {
+        rng.set_word_pos(word_pos);
+    }
+    Mutator::new(rng)
 }
 
 impl<M, T, H, R> FuzzEngine<M, T, H, R>
@@ -52,35 +235,698 @@ where
     H: HealthCheck,
     R: ResetHandler,
 {
-    pub(crate) fn new(transport: T, healthcheck: H, resethandler: R, output: Output) -> Self {
-        let seed = [0; 32];
-        let rng = rand_chacha::ChaChaRng::from_seed(seed);
+    /// Creates a new engine for the given configuration with a first target. Further targets
+    /// can be added with `add_worker`.
+    /// # Errors
+    /// Returns an error if the schedule of the configuration is empty or the output directory
+    /// could not be initialized.
+    pub fn new(
+        config: FuzzConfig,
+        transport: T,
+        healthcheck: H,
+        resethandler: R,
+    ) -> ProFuzzResult<Self> {
+        if config.rounds_per_seed == 0 || config.mutations_per_round == 0 {
+            return Err(ProFuzzError::Custom {
+                err_msg: "rounds per seed and mutations per round must be at least 1".to_string(),
+            });
+        }
+        let output = Output::init(config.out_dir.clone(), config.auto_resume)?;
+        let next_crash_id = output.next_crash_id()?;
+        let next_timeout_id = output.next_timeout_id()?;
+        let seed_requested = config.seed.is_some();
+        let seed = config.seed.unwrap_or_else(rand::random);
+        let stats: StatsType = Arc::default();
+
+        let mut engine = Self {
+            workers: vec![],
+            campaign: Campaign {
+                control: FuzzControl::new(stats.clone()),
+                observers: Mutex::default(),
+                collectors: Arc::default(),
+                stats,
+                output,
+                seed,
+                state: Mutex::new(CampaignState {
+                    queue: Vec::default(),
+                    queue_position: 0,
+                    crash_clusters: CrashClusters::new(DEFAULT_THRESHOLD),
+                    unique_responses: HashSet::default(),
+                    rng_positions: vec![],
+                    last_state_flush: Instant::now(),
+                    stop_reason: None,
+                    next_crash_id,
+                    next_timeout_id,
+                }),
+                config,
+                session_started: Instant::now(),
+                execs_at_start: 0,
+                new_crashes: AtomicUsize::new(0),
+                responses_at_start: 0,
+            },
+            seed_requested,
+            logger: None,
+        };
+        engine.add_worker(transport, healthcheck, resethandler);
+        Ok(engine)
+    }
 
-        Self {
+    /// Adds another identical target which is fuzzed in parallel to the existing ones.
+    pub fn add_worker(&mut self, transport: T, healthcheck: H, resethandler: R) {
+        let id = self.workers.len();
+        self.workers.push(Worker {
+            id,
             transport,
             healthcheck,
             resethandler,
-            output,
-            mutator: Mutator::new(rng),
-            unique_responses: HashSet::default(),
-            unique_crashes: HashSet::default(),
-            last_send_buffers: VecDeque::default(),
+            mutator: worker_mutator(self.campaign.seed, id, None),
+            history: SendHistory::new(self.campaign.config.history),
             read_buffer: vec![0; 3000],
-            stats: Arc::default(),
-            queue: VecDeque::default(),
+            healthcheck_requests_seen: 0,
+            last_healthcheck_at: None,
+            written_at: None,
+        });
+        if let Ok(mut state) = self.campaign.state.lock() {
+            state.rng_positions.push(0);
+        }
+    }
+
+    /// Returns a handle to pause, resume or stop the engine and to subscribe to its events.
+    #[must_use]
+    pub fn control(&self) -> FuzzControl {
+        self.campaign.control.clone()
+    }
+
+    /// Registers an observer which is notified about new responses, crashes, resets and
+    /// finished cycles.
+    pub fn add_observer<O: FuzzObserver + 'static>(&mut self, observer: O) {
+        self.add_boxed_observer(Box::new(observer));
+    }
+
+    pub(crate) fn add_boxed_observer(&mut self, observer: Box<dyn FuzzObserver>) {
+        if let Ok(mut observers) = self.campaign.observers.lock() {
+            observers.push(observer);
+        }
+    }
+
+    /// Registers a collector which is invoked for every stored crash before the target is
+    /// reset.
+    pub fn add_collector<C: CrashCollector + 'static>(&mut self, collector: C) {
+        self.add_boxed_collector(Box::new(collector));
+    }
+
+    pub(crate) fn add_boxed_collector(&mut self, collector: Box<dyn CrashCollector>) {
+        if let Ok(mut collectors) = self.campaign.collectors.lock() {
+            collectors.push(collector);
+        }
+    }
+
+    /// Sets the logger, so the logs are shown in the TUI if it is enabled.
+    pub fn set_logger(&mut self, logger: Logger) {
+        self.logger = Some(logger);
+    }
+
+    fn load_initial_corpus(in_dir: &Path) -> Result<Vec<M>, ProFuzzError> {
+        let corpuses = fs::read_dir(in_dir)?;
+
+        let mut initial_corpus = vec![];
+
+        for corpus in corpuses {
+            let corpus = corpus?;
+            if !corpus.file_type()?.is_file() {
+                continue;
+            }
+            let file = File::open(corpus.path())?;
+            let mut buffer = Vec::new();
+            BufReader::new(file).read_to_end(&mut buffer)?;
+            if let Some(corpus) = M::from_bytes(buffer) {
+                initial_corpus.push(corpus);
+            } else {
+                tracing::error!("Could not load corpus file: {}", corpus.path().display());
+            }
+        }
+        Ok(initial_corpus)
+    }
+
+    /// Restores the queue and the unique responses from the output directory.
+    /// Returns false if there was no queue to resume from.
+    fn restore_queue(&mut self) -> Result<bool, ProFuzzError> {
+        let Some(saved) = SavedQueue::load(&self.campaign.output)? else {
+            return Ok(false);
+        };
+
+        let Ok(mut state) = self.campaign.state.lock() else {
+            return Ok(false);
+        };
+
+        state.unique_responses = saved.unique_responses.into_iter().collect();
+        state.queue = saved
+            .entries
+            .into_iter()
+            .filter_map(|entry| {
+                let Some(corpus) = M::from_bytes(entry.corpus) else {
+                    tracing::error!("Could not load corpus from the saved queue.");
+                    return None;
+                };
+                Some(QueueElement {
+                    corpus,
+                    response_hash: entry.response_hash,
+                    found_at_execution: entry.found_at_execution,
+                })
+            })
+            .collect();
+
+        if state.queue.is_empty() {
+            return Ok(false);
+        }
+        if saved.position < state.queue.len() {
+            state.queue_position = saved.position;
+        }
+
+        tracing::info!(
+            "Resumed queue with {} elements and {} unique responses.",
+            state.queue.len(),
+            state.unique_responses.len()
+        );
+        Ok(true)
+    }
+
+    /// Restores the crash clusters from `clusters.json`, so a crash found again after resuming
+    /// is not counted as unique and the clusters of the `dedup` command are kept.
+    fn restore_crash_clusters(&mut self) -> Result<(), ProFuzzError> {
+        let clusters = CrashClusters::load::<M>(&self.campaign.output)?;
+        if clusters.len() > 0 {
+            tracing::info!("Resumed {} crash clusters.", clusters.len());
+        }
+        if let Ok(mut state) = self.campaign.state.lock() {
+            state.crash_clusters = clusters;
+        }
+        Ok(())
+    }
+
+    /// Restores the RNG from the output directory so the mutations continue where the last
+    /// session stopped. If there is no checkpoint the current seed is stored.
+    fn restore_rng(&mut self) -> Result<(), ProFuzzError> {
+        if let Some(saved) = RngState::load(&self.campaign.output)? {
+            if self.seed_requested && saved.seed != self.campaign.seed {
+                tracing::warn!(
+                    "Ignoring the stored seed {} as the seed {} was given.",
+                    saved.seed,
+                    self.campaign.seed
+                );
+            } else {
+                self.campaign.seed = saved.seed;
+                for worker in &mut self.workers {
+                    let word_pos = saved.word_positions.get(worker.id).copied();
+                    worker.mutator = worker_mutator(saved.seed, worker.id, word_pos);
+                }
+                if let Ok(mut state) = self.campaign.state.lock() {
+                    for (i, word_pos) in saved.word_positions.iter().enumerate() {
+                        if let Some(position) = state.rng_positions.get_mut(i) {
+                            *position = *word_pos;
+                        }
+                    }
+                }
+                tracing::info!("Resuming with seed {}.", saved.seed);
+                return Ok(());
+            }
+        }
+        for worker in &mut self.workers {
+            worker.mutator = worker_mutator(self.campaign.seed, worker.id, None);
+        }
+        tracing::info!("Using seed {}.", self.campaign.seed);
+        RngState {
+            seed: self.campaign.seed,
+            word_positions: vec![0; self.workers.len()],
+        }
+        .save(&self.campaign.output)
+    }
+
+    #[allow(clippy::too_many_lines)]
+    /// Starts the main fuzzing loop, and if enabled the TUI. Returns when the fuzzer was stopped
+    /// or the budget is exhausted.
+    /// # Errors
+    /// Returns an error if the initial health check fails, no corpus was found or a
+    /// `ResetHandler` failed.
+    pub async fn fuzz(&mut self) -> Result<FuzzSummary, ProFuzzError> {
+        tracing::info!("Performing healthcheck bevore starting.");
+        for worker in &mut self.workers {
+            // Test if it is possible to connect to the target
+            worker.transport.connect().await?;
+            worker.transport.close().await?;
+            if !worker.healthcheck.is_ok().await? {
+                return Err(ProFuzzError::ConnectionFailed {
+                    err_msg: format!(
+                        "Initial healthcheck of {} was not successfull. Exiting.",
+                        worker.transport.title()
+                    ),
+                });
+            }
+        }
+
+        let targets: Vec<TargetInfo> = self
+            .workers
+            .iter()
+            .map(|worker| TargetInfo {
+                worker: worker.id,
+                title: worker.transport.title(),
+                transport: worker.transport.metadata(),
+            })
+            .collect();
+        TargetInfo::save_all(&targets, &self.campaign.output)?;
+
+        // load queue from the output to resume from the old state
+        self.restore_rng()?;
+        let resumed = self.restore_queue()?;
+        self.restore_crash_clusters()?;
+
+        let mut initial_corpus = vec![];
+        if !resumed {
+            initial_corpus = Self::load_initial_corpus(&self.campaign.config.in_dir)?;
+
+            if initial_corpus.is_empty() {
+                return Err(ProFuzzError::Custom {
+                    err_msg: "No input corpus found!.".to_owned(),
+                });
+            }
+        }
+
+        let mut ui_handler = None;
+        let stats = self.campaign.stats.clone();
+
+        {
+            let previous_stats = Stats::load(&self.campaign.output)?;
+            let clusters = self
+                .campaign
+                .state
+                .lock()
+                .map(|state| state.crash_clusters.len())
+                .unwrap_or_default();
+            if let Ok(mut stats) = stats.write() {
+                if let Some(previous_stats) = previous_stats {
+                    tracing::info!(
+                        "Resuming stats with {} executions.",
+                        previous_stats.total_executions
+                    );
+                    stats.merge(&previous_stats);
+                }
+                // the unique crashes are the clusters, which could have been changed by `dedup`
+                stats.total_crashes = clusters;
+                // `FuzzControl::stop` could have been called before the engine was started
+                stats.running = !self.campaign.control.take_stop_request();
+                stats.workers = self.workers.len();
+                stats.title = self.workers[0].transport.title();
+                if self.workers.len() > 1 {
+                    stats.title = format!("{} +{} more", stats.title, self.workers.len() - 1);
+                }
+                if stats.started.is_none() {
+                    stats.started = Some(SerializableInstant::now());
+                }
+                self.campaign.session_started = Instant::now();
+                self.campaign.execs_at_start = stats.total_executions;
+                self.campaign.responses_at_start = stats.total_unique_responses;
+            }
+            ExecsPerSecond::start(stats.clone());
+        }
+
+        let signal_handler = self
+            .campaign
+            .config
+            .handle_signals
+            .then(|| spawn_signal_handler(stats.clone()));
+
+        // Spawn a thread for the TUI if enabled
+        if self.campaign.config.enable_ui {
+            let stats = stats.clone();
+            let logger = self.logger.clone();
+            ui_handler = Some(std::thread::spawn(move || {
+                if let Some(logger) = &logger {
+                    logger.enable_tui();
+                }
+                show_ui(&stats);
+                if let Some(logger) = &logger {
+                    logger.disable_tui();
+                }
+            }));
+        }
+
+        if !resumed {
+            tracing::info!("Testing initial corpuse files");
+
+            for init_corpus in &initial_corpus {
+                if !self.campaign.is_running() {
+                    break;
+                }
+                self.workers[0]
+                    .send_corpus(init_corpus, &self.campaign)
+                    .await;
+            }
+
+            if let Ok(mut state) = self.campaign.state.lock() {
+                state.queue = initial_corpus
+                    .into_iter()
+                    .map(|x| QueueElement {
+                        corpus: x,
+                        response_hash: None,
+                        found_at_execution: 0,
+                    })
+                    .collect();
+            }
+        }
+
+        tracing::info!("Starting {} worker(s).", self.workers.len());
+        self.campaign.emit(FuzzEvent::Started);
+
+        let campaign = &self.campaign;
+        let results = futures::future::join_all(
+            self.workers
+                .iter_mut()
+                .map(|worker| worker.fuzz::<M>(campaign)),
+        )
+        .await;
+
+        if let Some(signal_handler) = signal_handler {
+            signal_handler.abort();
+        }
+
+        // every worker closed its transport, now store the state so the session can be resumed
+        tracing::info!("Saving state to the output directory.");
+        if let Ok(mut stats) = stats.write() {
+            stats.running = false;
+        }
+        // the stop request was handled, so the next session is not stopped right away
+        self.campaign.control.take_stop_request();
+        self.campaign.flush_state(true);
+
+        if let Some(ui_handler) = ui_handler {
+            let _ = ui_handler.join();
+        }
+        results.into_iter().collect::<Result<(), ProFuzzError>>()?;
+
+        let summary = self.campaign.summary();
+        self.campaign.emit(FuzzEvent::Stopped {
+            reason: summary.stop_reason,
+        });
+        Ok(summary)
+    }
+}
+
+impl<M> Campaign<M>
+where
+    M: Clone + Corpus + Mutable,
+{
+    fn is_running(&self) -> bool {
+        self.stats.read().is_ok_and(|stats| stats.running)
+    }
+
+    /// Returns the reason in case the budget of the current session is exhausted.
+    fn budget_exhausted(&self, stats: &Stats) -> Option<StopReason> {
+        let budget = &self.config.budget;
+        if let Some(max_time) = budget.max_time
+            && self.session_started.elapsed() >= max_time
+        {
+            return Some(StopReason::TimeLimit);
+        }
+        if let Some(max_execs) = budget.max_execs
+            && stats.total_executions - self.execs_at_start >= max_execs
+        {
+            return Some(StopReason::ExecutionLimit);
+        }
+        if budget.stop_on_first_crash && self.new_crashes.load(Ordering::Relaxed) > 0 {
+            return Some(StopReason::FirstCrash);
+        }
+        None
+    }
+
+    /// Stops all workers with the given reason.
+    fn stop(&self, stats: &mut Stats, reason: StopReason) {
+        tracing::info!("Stopping the fuzzer: {reason}.");
+        stats.running = false;
+        if let Ok(mut state) = self.state.lock() {
+            state.stop_reason = Some(reason);
+        }
+    }
+
+    fn summary(&self) -> FuzzSummary {
+        let stats = self
+            .stats
+            .read()
+            .map(|stats| stats.clone())
+            .unwrap_or_default();
+        let stop_reason = self
+            .state
+            .lock()
+            .ok()
+            .and_then(|state| state.stop_reason)
+            .unwrap_or(StopReason::Stopped);
+        FuzzSummary {
+            executions: stats.total_executions - self.execs_at_start,
+            total_executions: stats.total_executions,
+            new_crashes: self.new_crashes.load(Ordering::Relaxed),
+            total_crashes: stats.total_crashes,
+            new_responses: stats.total_unique_responses - self.responses_at_start,
+            corpus_count: stats.corpus_count,
+            cycles_done: stats.cylcles_done,
+            duration: self.session_started.elapsed(),
+            stop_reason,
+        }
+    }
+
+    /// Returns the next queue element which should be used as mutation root. Elements which are
+    /// added during a cycle are also used in the current cycle.
+    fn next_root(&self) -> Option<M> {
+        let mut cycle_done = None;
+        let root = {
+            let mut state = self.state.lock().ok()?;
+            if state.queue_position >= state.queue.len() {
+                state.queue_position = 0;
+                if let Ok(mut stats) = self.stats.write() {
+                    stats.cylcles_done += 1;
+                    cycle_done = Some(stats.cylcles_done);
+                }
+                tracing::info!("Starting new cycle.");
+            }
+            let root = state.queue.get(state.queue_position)?.corpus.clone();
+            state.queue_position += 1;
+            if let Ok(mut stats) = self.stats.write() {
+                stats.corpus_count = state.queue.len();
+            }
+            root
+        };
+        if let Some(cycles) = cycle_done {
+            self.emit(FuzzEvent::CycleDone { cycles });
+        }
+        Some(root)
+    }
+
+    /// Stores the artifacts of all registered collectors next to the crash. The collectors are
+    /// run on the blocking thread pool, so a slow collector does not stall the other workers.
+    async fn collect_artifacts(&self, crash: &CrashInfo) {
+        let collectors = self.collectors.clone();
+        let output = self.output.clone();
+        let crash = crash.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            let Ok(mut collectors) = collectors.lock() else {
+                return;
+            };
+            if collectors.is_empty() {
+                return;
+            }
+            let folder = match output.get_crash_artifacts_folder(crash.id) {
+                Ok(folder) => folder,
+                Err(err) => {
+                    tracing::error!("Could not create artifacts folder: {err}");
+                    return;
+                }
+            };
+            let mut names = HashSet::new();
+            for (index, collector) in collectors.iter_mut().enumerate() {
+                let mut name = collector.name();
+                // e.g. two `FileCopyCollector`s of different `core` files
+                if !names.insert(name.clone()) {
+                    name = format!("{index}-{name}");
+                    names.insert(name.clone());
+                }
+                match collector.collect(&crash) {
+                    Ok(artifact) => {
+                        if let Err(err) = fs::write(folder.join(&name), artifact) {
+                            tracing::error!("Could not store artifact {name}: {err}");
+                        }
+                    }
+                    Err(err) => tracing::error!("Collector {name} failed: {err}"),
+                }
+            }
+        })
+        .await;
+        if let Err(err) = result {
+            tracing::error!("Could not run the collectors: {err}");
+        }
+    }
+
+    /// Stores the crash and the updated clusters in the output directory. The crash includes the
+    /// history of several MiB, so it is written on the blocking thread pool.
+    /// Returns the id of the crash if it was stored.
+    async fn store_crash(&self, crash: Crash) -> Option<usize> {
+        let output = self.output.clone();
+        let saved = tokio::task::spawn_blocking(move || crash.save(&output).map(|()| crash.id))
+            .await
+            .map_err(|err| ProFuzzError::Custom {
+                err_msg: format!("Could not store the crash: {err}"),
+            })
+            .and_then(|saved| saved)
+            .and_then(|id| {
+                if let Ok(state) = self.state.lock() {
+                    state.crash_clusters.save(&self.output)?;
+                }
+                Ok(id)
+            });
+        match saved {
+            Ok(id) => {
+                self.new_crashes.fetch_add(1, Ordering::Relaxed);
+                Some(id)
+            }
+            Err(err) => {
+                tracing::error!("{err}");
+                None
+            }
         }
     }
 
+    /// Executes the `on_crash` command, so the user can collect further information like serial
+    /// logs before the target is reset. Only the crashed worker waits for the command, the other
+    /// workers keep fuzzing. The command is killed after the `on_crash_timeout`.
+    async fn run_on_crash_command(&self, crash: &CrashInfo) {
+        let Some(command) = &self.config.on_crash else {
+            return;
+        };
+        tracing::info!("Running on-crash command for crash {}.", crash.id);
+        let crash_dir = self.output.get_crash_dir(crash.id);
+        let child = tokio::process::Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .env("PROFUZZ_CRASH_FILE", crash_dir.join(CRASH_META_FILE))
+            .env("PROFUZZ_CRASH_BUFFER", crash_dir.join(CRASH_BUFFER_FILE))
+            .env("PROFUZZ_CRASH_ID", crash.id.to_string())
+            .env(
+                "PROFUZZ_CRASH_TIME",
+                crash
+                    .detected_at
+                    .to_rfc3339_opts(SecondsFormat::Micros, true),
+            )
+            .env("PROFUZZ_OUTPUT_DIR", self.output.get_path())
+            .env("PROFUZZ_WORKER", crash.worker.to_string())
+            .env("PROFUZZ_TARGET", &crash.target)
+            .kill_on_drop(true)
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(err) => {
+                tracing::error!("Could not run on-crash command: {err}");
+                return;
+            }
+        };
+        match tokio::time::timeout(self.config.on_crash_timeout, child.wait()).await {
+            Ok(Ok(status)) if !status.success() => {
+                tracing::warn!("On-crash command exited with {status}.");
+            }
+            Ok(Err(err)) => tracing::error!("Could not run on-crash command: {err}"),
+            Ok(Ok(_)) => {}
+            Err(_) => {
+                tracing::warn!(
+                    "On-crash command did not finish within {:?}, killing it.",
+                    self.config.on_crash_timeout
+                );
+                if let Err(err) = child.kill().await {
+                    tracing::error!("Could not kill on-crash command: {err}");
+                }
+            }
+        }
+    }
+
+    /// Notifies the observers and all subscribers of the `FuzzControl` about the event. This
+    /// must not be called while holding a lock, as the observers could access the stats.
+    fn emit(&self, event: FuzzEvent) {
+        if let Ok(mut observers) = self.observers.lock() {
+            for observer in observers.iter_mut() {
+                match &event {
+                    FuzzEvent::NewResponse { worker, corpus } => {
+                        observer.on_new_response(*worker, corpus);
+                    }
+                    FuzzEvent::Crash(crash) => observer.on_crash(crash),
+                    FuzzEvent::Reset { worker } => observer.on_reset(*worker),
+                    FuzzEvent::CycleDone { cycles } => observer.on_cycle_done(*cycles),
+                    _ => {}
+                }
+            }
+        }
+        self.control.emit(event);
+    }
+
+    /// Writes the current state into the output directory so the session can be resumed. If
+    /// `force` is false this is only done every `STATE_FLUSH_INTERVAL`.
+    fn flush_state(&self, force: bool) {
+        let (queue, rng) = {
+            let Ok(mut state) = self.state.lock() else {
+                return;
+            };
+            if !force && state.last_state_flush.elapsed() < STATE_FLUSH_INTERVAL {
+                return;
+            }
+            state.last_state_flush = Instant::now();
+            let queue = SavedQueue {
+                entries: state
+                    .queue
+                    .iter()
+                    .map(|element| SavedQueueEntry {
+                        corpus: element.corpus.clone().to_bytes(),
+                        response_hash: element.response_hash.clone(),
+                        found_at_execution: element.found_at_execution,
+                    })
+                    .collect(),
+                position: state.queue_position,
+                unique_responses: state.unique_responses.iter().cloned().collect(),
+            };
+            let rng = RngState {
+                seed: self.seed,
+                word_positions: state.rng_positions.clone(),
+            };
+            (queue, rng)
+        };
+
+        if let Err(err) = queue.save(&self.output) {
+            tracing::error!("Could not save the queue: {err}");
+        }
+        if let Err(err) = rng.save(&self.output) {
+            tracing::error!("Could not save the rng state: {err}");
+        }
+        if let Ok(stats) = self.stats.read()
+            && let Err(err) = stats.save(&self.output)
+        {
+            tracing::error!("Could not save the stats: {err}");
+        }
+    }
+}
+
+impl<T, H, R> Worker<T, H, R>
+where
+    T: Transport,
+    H: HealthCheck,
+    R: ResetHandler,
+{
     /// Sends the current copurs to the target.
     /// When a response is returned from the target this is used to create
     /// coverage based new corpus files.
     /// It returnes false if it was not able to reset the current connection
-    async fn send_corpus(&mut self, corpus: &M) -> bool {
+    async fn send_corpus<M>(&mut self, corpus: &M, campaign: &Campaign<M>) -> bool
+    where
+        M: Clone + Corpus + Mutable,
+    {
         let mut backoff_time = 100;
         let mut reset_tried = 0;
+        self.written_at = None;
 
         loop {
-            if let Ok(mut stats) = self.stats.write() {
+            if let Ok(mut stats) = campaign.stats.write() {
                 stats.backoff_time = 0;
             }
 
@@ -92,17 +938,17 @@ where
             if backoff_time <= (1000 * 5) {
                 backoff_time *= 2;
             }
-            if let Ok(mut stats) = self.stats.write() {
+            if let Ok(mut stats) = campaign.stats.write() {
                 stats.executions_per_second.clear();
             }
-            if let Ok(mut stats) = self.stats.write() {
+            if let Ok(mut stats) = campaign.stats.write() {
                 stats.backoff_time = backoff_time;
             }
 
             if reset_tried > 3 {
                 return false;
             }
-            sleep(Duration::from_millis(backoff_time));
+            sleep(Duration::from_millis(backoff_time)).await;
         }
 
         let bytes = corpus.to_owned().to_bytes();
@@ -113,14 +959,13 @@ where
 
         // Sending fuzzing input to the connected target
         if let Err(err) = self.transport.write(&bytes).await {
-            if matches!(err, ProFuzzError::Timeout { .. })
-                && let Ok(mut stats) = self.stats.write()
-            {
-                stats.total_timeouts += 1;
+            if matches!(err, ProFuzzError::Timeout { .. }) {
+                self.store_timeout(bytes, true, campaign);
             }
             tracing::warn!("[WRITING] {err}");
             return false;
         }
+        self.written_at = Some(Utc::now());
 
         // try to read from the target
         match self.transport.read(&mut self.read_buffer).await {
@@ -136,7 +981,11 @@ where
                 hasher.update(&self.read_buffer[0..size]);
                 let result = hasher.finalize().to_vec();
 
-                if self.unique_responses.insert(result.clone()) {
+                let mut new_response = false;
+                if let Ok(mut state) = campaign.state.lock()
+                    && state.unique_responses.insert(result.clone())
+                {
+                    new_response = true;
                     let mut max_info_size = size;
                     let mut trunc = String::new();
                     if max_info_size > 5 {
@@ -149,23 +998,29 @@ where
                         &self.read_buffer[0..max_info_size]
                     );
 
-                    if let Ok(mut stats) = self.stats.write() {
+                    if let Ok(mut stats) = campaign.stats.write() {
                         stats.total_unique_responses += 1;
                         stats.last_new_path = Some(SerializableInstant::now());
                         stats.corpus_count += 3;
 
-                        self.queue.push_back(QueueElement {
+                        state.queue.push(QueueElement {
                             corpus: corpus.clone(),
+                            response_hash: Some(result),
+                            found_at_execution: stats.total_executions,
                         });
                     }
                 }
+                if new_response {
+                    campaign.emit(FuzzEvent::NewResponse {
+                        worker: self.id,
+                        corpus: bytes,
+                    });
+                }
                 let _ = self.transport.close().await;
             }
             Err(err) => {
-                if matches!(err, ProFuzzError::Timeout { .. })
-                    && let Ok(mut stats) = self.stats.write()
-                {
-                    stats.total_timeouts += 1;
+                if matches!(err, ProFuzzError::Timeout { .. }) {
+                    self.store_timeout(bytes, false, campaign);
                 }
                 tracing::warn!("[READING]: {err}");
                 return false;
@@ -174,239 +1029,313 @@ where
         true
     }
 
-    fn load_initial_corpus(in_dir: &PathBuf) -> Result<Vec<M>, ProFuzzError> {
-        let corpuses = fs::read_dir(in_dir)?;
-
-        let mut initial_corpus = vec![];
-
-        for corpus in corpuses {
-            let corpus = corpus?;
-            if !corpus.file_type()?.is_file() {
-                continue;
-            }
-            let file = File::open(corpus.path())?;
-            let mut buffer = Vec::new();
-            BufReader::new(file).read_to_end(&mut buffer)?;
-            if let Some(corpus) = M::from_bytes(buffer) {
-                initial_corpus.push(corpus);
-            } else {
-                tracing::error!("Could not load corpus file: {}", corpus.path().display());
+    /// Counts the timeout and stores the buffer which caused it, so it can be exported as a
+    /// finding. Only the first `MAX_STORED_TIMEOUTS` timeouts are stored.
+    fn store_timeout<M>(&self, buffer: Vec<u8>, writing: bool, campaign: &Campaign<M>)
+    where
+        M: Clone + Corpus + Mutable,
+    {
+        if let Ok(mut stats) = campaign.stats.write() {
+            stats.total_timeouts += 1;
+        }
+        let id = {
+            let Ok(mut state) = campaign.state.lock() else {
+                return;
+            };
+            if state.next_timeout_id >= MAX_STORED_TIMEOUTS {
+                return;
             }
+            state.next_timeout_id += 1;
+            state.next_timeout_id - 1
+        };
+        let stored = Timeout {
+            id,
+            buffer,
+            detected_at: Utc::now(),
+            writing,
+            worker: self.id,
+            target: self.transport.title(),
+        }
+        .save(&campaign.output);
+        if let Err(err) = stored {
+            tracing::error!("Could not store the timeout: {err}");
         }
-        Ok(initial_corpus)
     }
 
-    async fn do_healthcheck(&mut self, after_reset: bool) -> bool {
+    async fn do_healthcheck<M>(
+        &mut self,
+        after_reset: bool,
+        send_failed: bool,
+        campaign: &Campaign<M>,
+    ) -> bool
+    where
+        M: Clone + Corpus + Mutable,
+    {
         // check for unique crashes...
-        let mut failed = false;
-        if let Ok(is_ok) = self.healthcheck.is_ok().await {
-            if !is_ok {
-                failed = true;
-            }
-        } else {
-            failed = true;
-        }
+        let failure = match self.healthcheck.is_ok().await {
+            Ok(true) => None,
+            Ok(false) => Some(UNHEALTHY.to_string()),
+            Err(err) => Some(err.to_string()),
+        };
+        let failed = failure.is_some();
+        let checked_at = Utc::now();
+        campaign.emit(FuzzEvent::HealthCheck {
+            worker: self.id,
+            healthy: !failed,
+            checked_at,
+        });
         if failed {
             if after_reset {
                 return false;
             }
-            let len = self.last_send_buffers.len();
-            if len > 0
-                && let Some(buffer) = self.last_send_buffers.get(len - 1).cloned()
-            {
-                let crash = Crash {
-                    stats: self.stats.read().expect("").clone(),
-                    buffer: buffer.clone(),
-                    last_send_buffers: self.last_send_buffers.clone().into_iter().collect(),
-                };
+            if let Some(buffer) = self.history.last().map(<[u8]>::to_vec) {
+                let target = self.transport.title();
+                tracing::info!("Crash detected on {target}");
+                let sent_at = self.history.last_sent_at();
+                // only in case sending failed the health check ran right after the triggering
+                // buffer, otherwise the latency mostly depends on the health check interval
+                let latency = sent_at
+                    .filter(|_| send_failed)
+                    .map(|sent_at| checked_at - sent_at);
+                let features = CrashFeatures::new::<M>(&buffer, failure.clone(), latency);
 
-                if let Err(err) = crash.save(&self.output) {
-                    tracing::error!("{err}");
+                let mut unique = false;
+                let mut crash = None;
+                if let Ok(mut state) = campaign.state.lock() {
+                    let id = state.next_crash_id;
+                    state.next_crash_id += 1;
+                    let (cluster, new) = state.crash_clusters.insert(id, features);
+                    unique = new;
+                    crash = Some(Crash {
+                        id,
+                        stats: campaign.stats.read().expect("").clone(),
+                        buffer: buffer.clone(),
+                        history: self.history.to_vec(),
+                        detected_at: Some(checked_at),
+                        sent_at,
+                        healthcheck_failure: failure,
+                        send_failed,
+                        last_healthcheck_at: self.last_healthcheck_at,
+                        worker: self.id,
+                        target: target.clone(),
+                        reproducibility: None,
+                        cluster: Some(cluster),
+                    });
+                    if unique && let Ok(mut ok) = campaign.stats.write() {
+                        ok.total_crashes += 1;
+                        ok.last_unique_crash = Some(SerializableInstant::now());
+                    }
                 }
-
-                if self.unique_crashes.insert(buffer)
-                    && let Ok(mut ok) = self.stats.write()
-                {
-                    ok.total_crashes += 1;
-                    ok.last_unique_crash = Some(SerializableInstant::now());
-                    return false;
+                let id = match crash {
+                    Some(crash) => campaign.store_crash(crash).await,
+                    None => None,
+                };
+                if let Some(id) = id {
+                    let crash = CrashInfo {
+                        id,
+                        worker: self.id,
+                        target,
+                        buffer,
+                        unique,
+                        detected_at: checked_at,
+                    };
+                    campaign.collect_artifacts(&crash).await;
+                    campaign.run_on_crash_command(&crash).await;
+                    campaign.emit(FuzzEvent::Crash(crash));
                 }
             }
             return false;
         }
-        if let Ok(mut ok) = self.stats.write() {
+        self.last_healthcheck_at = Some(checked_at);
+        if let Ok(mut ok) = campaign.stats.write() {
             ok.last_healt_check = Some(SerializableInstant::now());
         }
-        true
-    }
-
-    #[allow(clippy::too_many_lines)]
-    /// Starts the main fuzzing loop, and if enabled the TUI.
-    /// # Errors
-    pub async fn fuzz(
-        &mut self,
-        enable_ui: bool,
-        in_dir: &PathBuf,
-        logger: Option<Logger>,
-    ) -> Result<(), ProFuzzError> {
+        if let Ok(mut state) = campaign.state.lock()
+            && let Some(position) = state.rng_positions.get_mut(self.id)
         {
-            tracing::info!("Performing healthcheck bevore starting.");
-            // Test if it is possible to connect to the target
-            self.transport.connect().await?;
-            self.transport.close().await?;
-            if !self.healthcheck.is_ok().await? {
-                return Err(ProFuzzError::ConnectionFailed {
-                    err_msg: "Initial healthcheck was not successfull. Exiting.".to_owned(),
-                });
-            }
-        }
-
-        // load queue from the output to resume from the old state
-        let initial_corpus = Self::load_initial_corpus(in_dir)?;
-
-        if initial_corpus.is_empty() {
-            return Err(ProFuzzError::Custom {
-                err_msg: "No input corpus found!.".to_owned(),
-            });
+            *position = self.mutator.rng().get_word_pos();
         }
+        true
+    }
 
-        let mut ui_handler = None;
-
-        {
-            if let Ok(mut stats) = self.stats.write() {
-                stats.running = true;
-                stats.title = self.transport.title();
-                stats.started = Some(SerializableInstant::now());
+    /// Main fuzzing loop of a single worker. On error all other workers are stopped as well.
+    async fn fuzz<M>(&mut self, campaign: &Campaign<M>) -> Result<(), ProFuzzError>
+    where
+        M: Clone + Corpus + Mutable,
+    {
+        let result = self.fuzz_loop(campaign).await;
+        if let Err(err) = &result {
+            tracing::error!("Worker {} stopped: {err}", self.id);
+            if let Ok(mut stats) = campaign.stats.write() {
+                stats.running = false;
             }
-            ExecsPerSecond::start(self.stats.clone());
-        }
-
-        // Spawn a thread for the TUI if enabled
-        if enable_ui {
-            let stats = self.stats.clone();
-            ui_handler = Some(std::thread::spawn(move || {
-                if let Some(logger) = &logger {
-                    logger.enable_tui();
-                }
-                show_ui(&stats);
-                if let Some(logger) = &logger {
-                    logger.disable_tui();
-                }
-            }));
-        }
-
-        tracing::info!("Testing initial corpuse files");
-
-        for init_corpus in &initial_corpus {
-            self.send_corpus(init_corpus).await;
-        }
-
-        self.queue = VecDeque::from(
-            initial_corpus
-                .into_iter()
-                .map(|x| QueueElement { corpus: x })
-                .collect::<Vec<_>>(),
-        );
-
-        if let Ok(mut stats) = self.stats.write() {
-            stats.corpus_count = self.queue.len();
         }
+        let _ = self.transport.close().await;
+        result
+    }
 
-        let mut running = true;
+    async fn fuzz_loop<M>(&mut self, campaign: &Campaign<M>) -> Result<(), ProFuzzError>
+    where
+        M: Clone + Corpus + Mutable,
+    {
+        let mut running = campaign.is_running();
 
         // when the transport layer never fails like in case of UDP
         // a health check is triggered every 5 seconds
         // Because a healt check does takes some time this is not triggered every time a message is
         // send
         let mut last_health_check = Instant::now();
+        let mut executions_since_healthcheck = 0;
 
         while running {
-            if let Ok(mut stats) = self.stats.write() {
-                stats.corpus_count = self.queue.len();
-            }
-
-            let mut next_cycle = VecDeque::new();
-
-            tracing::info!("Starting new cycle.");
+            let Some(root) = campaign.next_root() else {
+                break;
+            };
 
-            while let Some(element) = self.queue.pop_front() {
-                // each element should be used multiple times as "root" and the mutation should be
-                // started from there
-                for _ in 0..50 {
-                    // Start from the source corpus
-                    let mut corpus = element.corpus.clone();
-                    // and then mutate this source corpus 100x
-                    for _ in 0..1000 {
-                        if !running {
-                            break;
-                        }
+            // each element should be used multiple times as "root" and the mutation should be
+            // started from there
+            for _ in 0..campaign.config.rounds_per_seed {
+                // Start from the source corpus
+                let mut corpus = root.clone();
+                // and then stack the mutations on top of each other
+                for _ in 0..campaign.config.mutations_per_round {
+                    // wait while the engine is paused by the `FuzzControl`
+                    while campaign.control.is_paused() && campaign.is_running() {
+                        sleep(Duration::from_millis(100)).await;
+                    }
+                    if !running || !campaign.is_running() {
+                        running = false;
+                        break;
+                    }
 
-                        // if let Some(state) = &element.state {
-                        //     self.mutator.set_chances(state.chance.clone());
-                        // }
+                    // if let Some(state) = &element.state {
+                    //     self.mutator.set_chances(state.chance.clone());
+                    // }
 
-                        corpus.mutate(&mut self.mutator);
+                    corpus.mutate(&mut self.mutator);
 
-                        // store elements to send into a buffer so we can easily reproduce a crash
-                        self.last_send_buffers.push_front(corpus.clone().to_bytes());
+                    executions_since_healthcheck += 1;
 
-                        let sending_without_error = self.send_corpus(&corpus).await;
+                    let sending_without_error = self.send_corpus(&corpus, campaign).await;
 
-                        let mut after_reset = false;
-                        loop {
-                            if let Ok(mut stats) = self.stats.write() {
-                                stats.total_executions += 1;
-                                stats.executions_per_second.add();
-                                if !stats.running {
-                                    running = false;
-                                    break;
-                                }
-                            }
+                    // store sent elements into a buffer so we can easily reproduce a crash. A
+                    // buffer which could not be sent is stored with the time sending failed.
+                    self.history.push(
+                        &corpus.clone().to_bytes(),
+                        self.written_at.unwrap_or_else(Utc::now),
+                    );
 
-                            // as a healthcheck does slow down the fuzzing process try to do it not
-                            // every time
-                            if self.last_send_buffers.len() < 20_000
-                                && last_health_check.elapsed().as_secs() <= 4
-                                && sending_without_error
+                    let mut after_reset = false;
+                    loop {
+                        if let Ok(mut stats) = campaign.stats.write() {
+                            stats.total_executions += 1;
+                            stats.executions_per_second.add();
+                            if stats.running
+                                && let Some(reason) = campaign.budget_exhausted(&stats)
                             {
-                                break;
+                                campaign.stop(&mut stats, reason);
                             }
-
-                            if self.do_healthcheck(after_reset).await {
-                                last_health_check = Instant::now();
-                                // safe also packets which where send before the healthcheck in
-                                // case they are also needed
-                                self.last_send_buffers.truncate(5_000);
-                                // self.last_send_buffers.clear();
+                            if !stats.running {
+                                running = false;
                                 break;
                             }
+                        }
 
-                            tracing::info!("Resethandler triggered");
+                        // as a healthcheck does slow down the fuzzing process try to do it not
+                        // every time
+                        let healthcheck_requests = campaign.control.healthcheck_requests();
+                        if executions_since_healthcheck < campaign.config.healthcheck_executions
+                            && last_health_check.elapsed() < campaign.config.healthcheck_interval
+                            && sending_without_error
+                            && healthcheck_requests == self.healthcheck_requests_seen
+                        {
+                            break;
+                        }
+                        self.healthcheck_requests_seen = healthcheck_requests;
 
-                            // after an reset wait until the healthcheck shows good again
-                            after_reset = true;
-                            self.resethandler.reset().await?;
-                            sleep(Duration::from_secs(1));
+                        if self
+                            .do_healthcheck(after_reset, !sending_without_error, campaign)
+                            .await
+                        {
+                            last_health_check = Instant::now();
+                            executions_since_healthcheck = 0;
+                            campaign.flush_state(false);
+                            // keep some buffers which were sent before the health check in
+                            // case they are also needed to reproduce a later crash
+                            self.history.truncate(HISTORY_KEPT_AFTER_HEALTHCHECK);
+                            break;
                         }
+
+                        tracing::info!("Resethandler of {} triggered", self.transport.title());
+                        campaign.emit(FuzzEvent::Reset { worker: self.id });
+
+                        // after an reset wait until the healthcheck shows good again
+                        after_reset = true;
+                        self.resethandler.reset().await?;
+                        sleep(Duration::from_secs(1)).await;
                     }
                 }
-                // add all send elements to the queue
-                next_cycle.push_back(element);
             }
+        }
+        Ok(())
+    }
+}
 
-            if let Ok(mut stats) = self.stats.write() {
-                stats.cylcles_done += 1;
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{FakeTarget, Message, TempOutput};
+    use crate::types::RngState;
 
-            self.queue = next_cycle;
-        }
+    fn draw(mutator: &mut Mutator<ChaChaRng>) -> Vec<usize> {
+        (0..100).map(|_| mutator.gen_range(0, 1 << 20)).collect()
+    }
 
-        if let Ok(mut stats) = self.stats.write() {
-            stats.running = false;
+    #[test]
+    fn resumed_rng_continues_the_sequence() {
+        let mut mutator = worker_mutator(42, 1, None);
+        draw(&mut mutator);
+        let temp = TempOutput::with_legacy_crashes("rng", &[]);
+        RngState {
+            seed: 42,
+            word_positions: vec![0, mutator.rng().get_word_pos()],
         }
-        if let Some(ui_handler) = ui_handler {
-            let _ = ui_handler.join();
-        }
-        Ok(())
+        .save(&temp.output)
+        .expect("save rng");
+        let expected = draw(&mut mutator);
+
+        let saved = RngState::load(&temp.output)
+            .expect("load rng")
+            .expect("stored rng");
+        let mut resumed = worker_mutator(saved.seed, 1, saved.word_positions.get(1).copied());
+        assert_eq!(draw(&mut resumed), expected);
+        // every worker uses its own stream
+        assert_ne!(
+            draw(&mut worker_mutator(42, 0, None)),
+            draw(&mut worker_mutator(42, 1, None))
+        );
+    }
+
+    #[tokio::test]
+    async fn stop_before_fuzz_returns_immediately() {
+        let dir = std::env::temp_dir().join(format!("profuzz-stop-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("in")).expect("create input dir");
+        fs::write(dir.join("in").join("seed"), [1, 2, 3]).expect("write seed");
+        let config = FuzzConfig {
+            in_dir: dir.join("in"),
+            out_dir: dir.join("out"),
+            ..FuzzConfig::default()
+        };
+        let target = FakeTarget::new(|_| false);
+        let mut engine: FuzzEngine<Message, _, _, _> =
+            FuzzEngine::new(config, target.clone(), target.clone(), target).expect("engine");
+        engine.control().stop();
+
+        let summary = engine.fuzz().await.expect("fuzz");
+        assert_eq!(summary.executions, 0);
+        assert_eq!(summary.stop_reason, StopReason::Stopped);
+        assert!(!engine.control().is_running());
+        let _ = fs::remove_dir_all(&dir);
     }
 }
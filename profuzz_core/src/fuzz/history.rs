@@ -0,0 +1,253 @@
+use crate::fuzz::config::HistoryLimit;
+use crate::types::SentBuffer;
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+
+/// Every n-th entry is stored in full, which bounds the length of a chain of deltas. The
+/// history is only restored as a whole by `to_vec`, which applies each delta once.
+const KEYFRAME_INTERVAL: usize = 64;
+
+enum Entry {
+    Full(Vec<u8>),
+    /// Difference to the previous buffer: the new length and a sequence of patches, each
+    /// encoded as offset (u32 LE), length (u16 LE) and the changed bytes
+    Delta {
+        len: usize,
+        patches: Vec<u8>,
+    },
+}
+
+impl Entry {
+    fn size(&self) -> usize {
+        match self {
+            Entry::Full(buffer) => buffer.len(),
+            Entry::Delta { patches, .. } => patches.len(),
+        }
+    }
+}
+
+/// Memory-bounded ring buffer of the buffers sent to a target. As the stacked mutations only
+/// change a few bytes between two executions, most buffers are stored as delta to their
+/// predecessor.
+pub(crate) struct SendHistory {
+    entries: VecDeque<Entry>,
+    /// Wall-clock time when each buffer was sent
+    sent_at: VecDeque<DateTime<Utc>>,
+    limit: HistoryLimit,
+    bytes: usize,
+    /// Entries since the last full entry
+    since_keyframe: usize,
+    last: Vec<u8>,
+}
+
+impl SendHistory {
+    pub(crate) fn new(limit: HistoryLimit) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            sent_at: VecDeque::new(),
+            limit,
+            bytes: 0,
+            since_keyframe: 0,
+            last: vec![],
+        }
+    }
+
+    /// Returns the most recently sent buffer.
+    pub(crate) fn last(&self) -> Option<&[u8]> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        Some(&self.last)
+    }
+
+    /// Returns when the most recently sent buffer was sent.
+    pub(crate) fn last_sent_at(&self) -> Option<DateTime<Utc>> {
+        self.sent_at.back().copied()
+    }
+
+    /// Adds a buffer with the time it was written to the transport.
+    pub(crate) fn push(&mut self, buffer: &[u8], sent_at: DateTime<Utc>) {
+        let mut entry = None;
+        if !self.entries.is_empty() && self.since_keyframe + 1 < KEYFRAME_INTERVAL {
+            let patches = diff(&self.last, buffer);
+            if patches.len() < buffer.len() {
+                entry = Some(Entry::Delta {
+                    len: buffer.len(),
+                    patches,
+                });
+                self.since_keyframe += 1;
+            }
+        }
+        let entry = entry.unwrap_or_else(|| {
+            self.since_keyframe = 0;
+            Entry::Full(buffer.to_vec())
+        });
+        self.bytes += entry.size() + size_of::<DateTime<Utc>>();
+        self.entries.push_back(entry);
+        self.sent_at.push_back(sent_at);
+        self.last.clear();
+        self.last.extend_from_slice(buffer);
+
+        while self.entries.len() > 1 && self.exceeds_limit() {
+            self.pop_oldest();
+        }
+    }
+
+    /// Drops the oldest entries until at most `len` entries are left.
+    pub(crate) fn truncate(&mut self, len: usize) {
+        while self.entries.len() > len {
+            self.pop_oldest();
+        }
+    }
+
+    /// Returns all buffers, the oldest first.
+    pub(crate) fn to_vec(&self) -> Vec<SentBuffer> {
+        let mut buffers: Vec<SentBuffer> = Vec::with_capacity(self.entries.len());
+        for (entry, sent_at) in self.entries.iter().zip(&self.sent_at) {
+            let buffer = match entry {
+                Entry::Full(buffer) => buffer.clone(),
+                Entry::Delta { len, patches } => {
+                    let mut buffer = buffers.last().map(|b| b.buffer.clone()).unwrap_or_default();
+                    apply(&mut buffer, *len, patches);
+                    buffer
+                }
+            };
+            buffers.push(SentBuffer {
+                sent_at: Some(*sent_at),
+                buffer,
+            });
+        }
+        buffers
+    }
+
+    fn exceeds_limit(&self) -> bool {
+        self.limit
+            .max_entries
+            .is_some_and(|max| self.entries.len() > max)
+            || self.limit.max_bytes.is_some_and(|max| self.bytes > max)
+    }
+
+    /// Drops the oldest entry. The oldest entry is always stored in full, so the following
+    /// entry is converted to a full entry in case it is a delta.
+    fn pop_oldest(&mut self) {
+        let Some(Entry::Full(mut buffer)) = self.entries.pop_front() else {
+            return;
+        };
+        self.sent_at.pop_front();
+        self.bytes -= buffer.len() + size_of::<DateTime<Utc>>();
+        if let Some(next) = self.entries.front_mut()
+            && let Entry::Delta { len, patches } = next
+        {
+            self.bytes -= patches.len();
+            apply(&mut buffer, *len, patches);
+            self.bytes += buffer.len();
+            *next = Entry::Full(buffer);
+        }
+        if self.entries.len() <= self.since_keyframe {
+            self.since_keyframe = self.entries.len().saturating_sub(1);
+        }
+    }
+}
+
+/// Encodes the changed bytes from `old` to `new` as patches.
+fn diff(old: &[u8], new: &[u8]) -> Vec<u8> {
+    let mut patches = vec![];
+    let mut i = 0;
+    while i < new.len() {
+        if old.get(i) == Some(&new[i]) {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < new.len() && old.get(i) != Some(&new[i]) && i - start < usize::from(u16::MAX) {
+            i += 1;
+        }
+        let (Ok(offset), Ok(len)) = (u32::try_from(start), u16::try_from(i - start)) else {
+            // buffers larger than 4 GiB are never stored as delta
+            return new.to_vec();
+        };
+        patches.extend_from_slice(&offset.to_le_bytes());
+        patches.extend_from_slice(&len.to_le_bytes());
+        patches.extend_from_slice(&new[start..i]);
+    }
+    patches
+}
+
+fn apply(buffer: &mut Vec<u8>, len: usize, mut patches: &[u8]) {
+    buffer.resize(len, 0);
+    while let Some((header, rest)) = patches.split_first_chunk::<6>() {
+        let offset = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let count = usize::from(u16::from_le_bytes([header[4], header[5]]));
+        buffer[offset..offset + count].copy_from_slice(&rest[..count]);
+        patches = &rest[count..];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffers() -> Vec<Vec<u8>> {
+        let mut buffers = vec![vec![0u8; 100]];
+        for i in 0..300u16 {
+            let mut next = buffers.last().cloned().unwrap_or_default();
+            let [low, high] = i.to_le_bytes();
+            next[usize::from(i) % 80] = low;
+            if i % 7 == 0 {
+                next.push(high);
+            }
+            if i % 11 == 0 {
+                next.truncate(90);
+            }
+            buffers.push(next);
+        }
+        buffers
+    }
+
+    fn restore(history: &SendHistory) -> Vec<Vec<u8>> {
+        history.to_vec().into_iter().map(|b| b.buffer).collect()
+    }
+
+    #[test]
+    fn restores_all_buffers() {
+        let mut history = SendHistory::new(HistoryLimit {
+            max_entries: None,
+            max_bytes: None,
+        });
+        for buffer in buffers() {
+            history.push(&buffer, Utc::now());
+        }
+        assert_eq!(restore(&history), buffers());
+        assert_eq!(history.last(), buffers().last().map(Vec::as_slice));
+        assert!(history.bytes < buffers().iter().map(Vec::len).sum());
+    }
+
+    #[test]
+    fn drops_the_oldest_buffers() {
+        let mut history = SendHistory::new(HistoryLimit {
+            max_entries: Some(50),
+            max_bytes: None,
+        });
+        for buffer in buffers() {
+            history.push(&buffer, Utc::now());
+        }
+        let all = buffers();
+        assert_eq!(restore(&history), all[all.len() - 50..]);
+
+        let mut history = SendHistory::new(HistoryLimit {
+            max_entries: None,
+            max_bytes: Some(500),
+        });
+        for buffer in buffers() {
+            history.push(&buffer, Utc::now());
+        }
+        assert!(history.bytes <= 500);
+        let restored = restore(&history);
+        assert_eq!(restored, all[all.len() - restored.len()..]);
+
+        history.truncate(3);
+        assert_eq!(restore(&history), all[all.len() - 3..]);
+        history.truncate(0);
+        assert!(history.last().is_none());
+    }
+}
@@ -1,3 +1,6 @@
+use crate::error::ProFuzzResult;
+use crate::output::Output;
+use chrono::{DateTime, TimeDelta, Utc};
 use serde::{Deserialize, Serialize};
 use serde::{Deserializer, Serializer};
 use std::sync::{Arc, RwLock};
@@ -38,7 +41,8 @@ pub struct Stats {
     /// This field is `None` if no unique crash has been recorded.
     pub last_unique_crash: Option<SerializableInstant>,
 
-    /// The total number of crashes that have occurred during execution.
+    /// The number of unique crashes that have occurred during execution. Crashes which are
+    /// likely caused by the same bug are clustered and only counted once.
     pub total_crashes: usize,
 
     /// The total number of unique responses received during execution.
@@ -52,6 +56,60 @@ pub struct Stats {
 
     /// The backoff time in milliseconds, used to manage retries or delays in execution.
     pub backoff_time: u64,
+
+    /// The number of targets which are fuzzed in parallel.
+    #[serde(default)]
+    pub workers: usize,
+}
+
+impl Stats {
+    pub(crate) fn load(output: &Output) -> ProFuzzResult<Option<Stats>> {
+        let path = output.get_stats_file();
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(path)?;
+        Ok(Some(serde_json::from_str(&content)?))
+    }
+
+    pub(crate) fn save(&self, output: &Output) -> ProFuzzResult<()> {
+        let content = serde_json::to_string(self)?;
+        Output::write_atomic(&output.get_stats_file(), content.as_bytes())
+    }
+
+    /// Merges the statistics of a previous session into the current one, so the numbers are
+    /// cumulative for the whole campaign.
+    pub(crate) fn merge(&mut self, previous: &Stats) {
+        self.total_executions += previous.total_executions;
+        self.cylcles_done += previous.cylcles_done;
+        self.total_crashes += previous.total_crashes;
+        self.total_unique_responses += previous.total_unique_responses;
+        self.total_timeouts += previous.total_timeouts;
+        self.started = earliest(self.started, previous.started);
+        self.last_healt_check = latest(self.last_healt_check, previous.last_healt_check);
+        self.last_new_path = latest(self.last_new_path, previous.last_new_path);
+        self.last_unique_crash = latest(self.last_unique_crash, previous.last_unique_crash);
+    }
+}
+
+fn earliest(
+    a: Option<SerializableInstant>,
+    b: Option<SerializableInstant>,
+) -> Option<SerializableInstant> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(if *a <= *b { a } else { b }),
+        (a, b) => a.or(b),
+    }
+}
+
+fn latest(
+    a: Option<SerializableInstant>,
+    b: Option<SerializableInstant>,
+) -> Option<SerializableInstant> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(if *a >= *b { a } else { b }),
+        (a, b) => a.or(b),
+    }
 }
 
 const BUCKET_SIZE: usize = 10;
@@ -143,6 +201,23 @@ impl SerializableInstant {
     pub fn into_inner(self) -> Instant {
         self.0
     }
+    /// Returns the wall-clock time of the instant
+    #[must_use]
+    pub fn to_utc(&self) -> DateTime<Utc> {
+        let elapsed = TimeDelta::from_std(self.0.elapsed()).unwrap_or(TimeDelta::MAX);
+        Utc::now()
+            .checked_sub_signed(elapsed)
+            .unwrap_or(DateTime::UNIX_EPOCH)
+    }
+    /// Converts the wall-clock time into an instant. Times in the future are mapped to now.
+    fn from_utc(time: DateTime<Utc>) -> Self {
+        let elapsed = (Utc::now() - time).to_std().unwrap_or_default();
+        Self(
+            Instant::now()
+                .checked_sub(elapsed)
+                .unwrap_or_else(Instant::now),
+        )
+    }
 }
 
 impl Serialize for SerializableInstant {
@@ -150,20 +225,30 @@ impl Serialize for SerializableInstant {
     where
         S: Serializer,
     {
-        // Serialize as duration since process start
-        serializer.serialize_u128(self.0.elapsed().as_nanos())
+        // Serialize as wall-clock time, so it is still meaningful after a restart
+        self.to_utc().serialize(serializer)
     }
 }
 
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredInstant {
+    Utc(DateTime<Utc>),
+    /// Older versions stored the nanoseconds elapsed at the time of serialization. They were
+    /// written as `u128`, but an untagged enum can only buffer numbers up to `u64`.
+    Elapsed(u64),
+}
+
 impl<'de> Deserialize<'de> for SerializableInstant {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        let nanos = u128::deserialize(deserializer)?;
-        #[allow(clippy::cast_possible_truncation)]
-        if let Some(ok) = Instant::now().checked_sub(std::time::Duration::from_nanos(nanos as u64))
-        {
+        let nanos = match StoredInstant::deserialize(deserializer)? {
+            StoredInstant::Utc(time) => return Ok(SerializableInstant::from_utc(time)),
+            StoredInstant::Elapsed(nanos) => nanos,
+        };
+        if let Some(ok) = Instant::now().checked_sub(std::time::Duration::from_nanos(nanos)) {
             Ok(SerializableInstant::new(ok))
         } else {
             Ok(SerializableInstant::now())
@@ -198,3 +283,73 @@ impl From<SerializableInstant> for Instant {
         serializable.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempOutput;
+
+    fn ago(secs: u64) -> Option<SerializableInstant> {
+        Instant::now()
+            .checked_sub(Duration::from_secs(secs))
+            .map(SerializableInstant::new)
+    }
+
+    fn secs_ago(instant: Option<SerializableInstant>) -> Option<u64> {
+        instant.map(|instant| (instant.elapsed() + Duration::from_millis(500)).as_secs())
+    }
+
+    #[test]
+    fn merges_stored_stats() {
+        let temp = TempOutput::with_legacy_crashes("stats", &[]);
+        let previous = Stats {
+            total_executions: 100,
+            cylcles_done: 2,
+            total_crashes: 3,
+            total_unique_responses: 4,
+            total_timeouts: 5,
+            started: ago(60),
+            last_healt_check: ago(30),
+            last_new_path: ago(40),
+            ..Stats::default()
+        };
+        previous.save(&temp.output).expect("save stats");
+        let previous = Stats::load(&temp.output)
+            .expect("load stats")
+            .expect("stored stats");
+
+        let mut stats = Stats {
+            total_executions: 10,
+            total_crashes: 1,
+            total_timeouts: 1,
+            started: ago(5),
+            last_healt_check: ago(1),
+            last_unique_crash: ago(2),
+            ..Stats::default()
+        };
+        stats.merge(&previous);
+        assert_eq!(stats.total_executions, 110);
+        assert_eq!(stats.cylcles_done, 2);
+        assert_eq!(stats.total_crashes, 4);
+        assert_eq!(stats.total_unique_responses, 4);
+        assert_eq!(stats.total_timeouts, 6);
+        assert_eq!(secs_ago(stats.started), Some(60));
+        assert_eq!(secs_ago(stats.last_healt_check), Some(1));
+        assert_eq!(secs_ago(stats.last_new_path), Some(40));
+        assert_eq!(secs_ago(stats.last_unique_crash), Some(2));
+    }
+
+    #[test]
+    fn loads_legacy_stats() {
+        // older versions stored the instants as nanoseconds elapsed when they were saved
+        let mut legacy = serde_json::to_value(Stats::default()).expect("serialize stats");
+        legacy["started"] = serde_json::json!(20_000_000_000u64);
+        legacy["last_healt_check"] = serde_json::json!(3_000_000_000u64);
+        legacy["total_executions"] = serde_json::json!(7);
+        let stats: Stats = serde_json::from_value(legacy).expect("legacy stats");
+        assert_eq!(stats.total_executions, 7);
+        assert_eq!(secs_ago(stats.started), Some(20));
+        assert_eq!(secs_ago(stats.last_healt_check), Some(3));
+        assert!(stats.last_new_path.is_none());
+    }
+}
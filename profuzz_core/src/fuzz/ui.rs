@@ -53,7 +53,7 @@ fn format_duration(duration: Duration) -> String {
     let minutes = (total_secs % 3600) / 60;
     let seconds = total_secs % 60;
 
-    format!("{days} days, {hours} hrs, {minutes} min, {seconds} secs",)
+    format!("{days} days, {hours} hrs, {minutes} min, {seconds} secs")
 }
 
 fn format_number(num: u64) -> String {
@@ -154,6 +154,7 @@ impl Widget for &Ui {
                 ("cycles done", format_number(self.stats.cylcles_done as u64).white()),
                 ("corpus count", format_number(self.stats.corpus_count as u64).white()),
                 ("total responses", format_number(self.stats.total_unique_responses as u64).white()),
+                ("workers", format_number(self.stats.workers as u64).white()),
             ];
             let text = format_text_row(&text_rows);
 
@@ -199,7 +200,7 @@ impl Widget for &Ui {
                 total_crashes = format_number(self.stats.total_crashes as u64).red();
             }
 
-            let text_rows = [("total crashes", total_crashes), ("total timeouts", format_number(self.stats.total_timeouts as u64).white())];
+            let text_rows = [("unique crashes", total_crashes), ("total timeouts", format_number(self.stats.total_timeouts as u64).white())];
             let text = format_text_row(&text_rows);
 
             let border_set = symbols::border::Set {
@@ -21,14 +21,38 @@
 //! `profuzz_core` can be configured and started either in headless mode or using `start_cli` allowing the user
 //! to configure the different options using CLI.
 //!
-//! In case you want to use `profuzz` in headless mode, please have a look into the cli.rs to see
-//! how to set the fuzzing engine up directly.
+//! In case you want to use `profuzz` in headless mode, e.g. as one feature of a bigger scanner, the
+//! `FuzzEngine` can be created directly from a `FuzzConfig`. The `fuzz` function returns a
+//! `FuzzSummary` of the session instead of exiting the process:
+//!
+//! ```rs
+//! let config = FuzzConfig {
+//!     in_dir: PathBuf::from("corpus"),
+//!     out_dir: PathBuf::from("/tmp/fuzzing"),
+//!     auto_resume: true,
+//!     budget: Budget {
+//!         max_time: Some(Duration::from_secs(60 * 60)),
+//!         ..Budget::default()
+//!     },
+//!     ..FuzzConfig::default()
+//! };
+//! let mut engine: FuzzEngine<EtherMutatorOwned, _, _, _> =
+//!     FuzzEngine::new(config, transport, healthcheck, DummyResetHandler())?;
+//! let summary = engine.fuzz().await?;
+//! println!("{} new crashes", summary.new_crashes);
+//! ```
+//!
+//! While the engine is running it can be controlled with the cloneable `FuzzControl` handle
+//! returned by `engine.control()`. It allows to pause, resume or stop the engine, to request an
+//! immediate health check and to subscribe to the `FuzzEvent`s like new crashes or resets.
+//! Alternatively, a `FuzzObserver` can be registered with `engine.add_observer()` or
+//! `ProFuzzBuilder::add_observer` to be called directly from the fuzzing loop.
 //!
 //! The full example can be found in the [example](https://github.com/otsmr/profuzz/tree/main/example) folder.
 //!
 //! ```rs
 //! #[tokio::main]
-//! async fn main() {
+//! async fn main() -> ExitCode {
 //!     // Defining the Transport layer, in this case a raw linux socket.
 //!     let transport = RawSocketTransport::new("eth0");
 //!
@@ -51,10 +75,9 @@
 //!     let fuzzer = ProFuzzBuilder::new(transport, healthcheck, DummyResetHandler());
 //!
 //!     // Starting the CLI including a TUI, and defining the `Mutable` implementation struct that
-//!     // implements the mutation of the corpus files
-//!     if let Err(err) = fuzzer.start_cli::<EtherMutatorOwned>().await {
-//!         eprintln!("{err}");
-//!     }
+//!     // implements the mutation of the corpus files. The returned exit code reports if a crash
+//!     // was found.
+//!     fuzzer.start_cli::<EtherMutatorOwned>().await
 //! }
 //! ```
 //!
@@ -94,3 +117,7 @@ mod dangerous_numbers;
 
 /// A internal collection of different types
 pub(crate) mod types;
+
+/// Fixtures shared by the unit tests
+#[cfg(test)]
+mod test_utils;
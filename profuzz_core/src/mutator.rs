@@ -67,6 +67,20 @@ impl<R: Rng> Mutator<R> {
         }
     }
 
+    /// Creates a mutator which sets every number it mutates to zero. This is used to zero the
+    /// fields of a corpus when minimizing a crash.
+    pub(crate) fn zeroing(rng: R) -> Mutator<R> {
+        Mutator {
+            fake_rng: true,
+            ..Self::new(rng)
+        }
+    }
+
+    /// Returns the underlying RNG
+    pub(crate) fn rng(&self) -> &R {
+        &self.rng
+    }
+
     /// Mutates a number after randomly selecting a mutation strategy (see `MutatorOperation` for a list of strategies)
     /// If a min/max is specified then a new number in this range is chosen instead of performing
     /// a bit/arithmetic mutation
@@ -1,13 +1,33 @@
 //! The output directory contains the following files and folders:
 //!
-//! - `/crashes/`: All buffers which probably crashed the target.
-//! - `crashes.json`: Crash file which stores all informations to reproduce a potential crash
-//! - `stats.json`: Statistics which are shown in the TUI
-//! - `queue.json`: Current queue files which are used to crate new mutations
+//! - `/crashes/<id>/`: Every crash is stored in its own directory containing
+//!     - `crash.json`: Metadata of the crash like the stats and the crashed target
+//!     - `buffer.bin`: The last buffer sent before the crash was detected
+//!     - `history.bin`: The last buffers sent to the target with their UTC send time
+//!     - `reproducer.bin`: The buffer identified by the triage to crash the target, or
+//!       `reproducer.<i>.bin` in case a sequence of buffers is needed
+//!     - `triage.json`: The result of the triage
+//!     - `minimize.json`: The result of the minimization of the reproducer
+//!     - `report.md` or `report.html`: Human-readable report of the crash
+//!     - `artifacts/`: Artifacts of the crash collected by the `CrashCollector`s
+//! - `/timeouts/<id>/`: The first 1000 buffers after which the target did not respond in time,
+//!   each in its own directory containing
+//!     - `timeout.json`: Metadata of the timeout like the time and the target
+//!     - `buffer.bin`: The buffer sent before the timeout
+//! - `stats.json`: Statistics which are shown in the TUI, summed up over all resumed sessions
+//! - `queue.json`: Current queue files which are used to crate new mutations including all
+//!   unique responses, so the session can be resumed with `--auto-resume`
+//! - `rng.json`: Seed and stream position of the RNG used for the mutations
+//! - `target.json`: Title and connection parameters of the transport of every worker
+//! - `triage.json`, `regress.json`: Results of the last non-interactive triage and regression run
+//! - `clusters.json`: Crashes grouped by the bug which likely caused them, updated by the fuzzer
+//!   and recomputed by the `dedup` command
+//! - `findings.sarif`, `junit.xml`: Default files of the `export` command
 //!
 
 use crate::error::{ProFuzzError, ProFuzzResult};
-use std::path::PathBuf;
+use crate::types::Crash;
+use std::path::{Path, PathBuf};
 
 /// Manages the output directory like creating the needed folders,
 /// resuming a fuzzing session or storing informations
@@ -30,24 +50,100 @@ impl Output {
         }
 
         std::fs::create_dir_all(path.join("crashes"))?;
+        std::fs::create_dir_all(path.join("timeouts"))?;
 
-        Ok(Self { path })
+        let output = Self { path };
+        Crash::migrate(&output)?;
+        Ok(output)
     }
 
-    pub(crate) fn get_crash_file(&self) -> PathBuf {
+    pub(crate) fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Crash file used by older versions which stored all crashes in a single file
+    pub(crate) fn get_legacy_crash_file(&self) -> PathBuf {
         self.path.join("crashes.json")
     }
-    pub(crate) fn get_crash_folder(&self) -> PathBuf {
-        let dir = self.path.join("crashes");
-        std::fs::create_dir_all(&dir)
-            .unwrap_or_else(|_| panic!("Could not create crashes folder: {}!", dir.display()));
-        dir
+
+    pub(crate) fn get_crash_dir(&self, id: usize) -> PathBuf {
+        self.path.join("crashes").join(id.to_string())
+    }
+
+    /// Returns the ids of all stored crashes in ascending order.
+    pub(crate) fn get_crash_ids(&self) -> ProFuzzResult<Vec<usize>> {
+        self.get_ids("crashes")
+    }
+
+    pub(crate) fn next_crash_id(&self) -> ProFuzzResult<usize> {
+        Ok(self.get_crash_ids()?.last().map_or(0, |id| id + 1))
+    }
+
+    pub(crate) fn get_timeout_dir(&self, id: usize) -> PathBuf {
+        self.path.join("timeouts").join(id.to_string())
+    }
+
+    /// Returns the ids of all stored timeouts in ascending order.
+    pub(crate) fn get_timeout_ids(&self) -> ProFuzzResult<Vec<usize>> {
+        self.get_ids("timeouts")
+    }
+
+    pub(crate) fn next_timeout_id(&self) -> ProFuzzResult<usize> {
+        Ok(self.get_timeout_ids()?.last().map_or(0, |id| id + 1))
     }
 
-    // pub(crate) fn get_queue_file(&self) -> PathBuf {
-    //     self.path.join("queue.json")
-    // }
-    // pub(crate) fn get_stats_file(&self) -> PathBuf {
-    //     self.path.join("stats.json")
-    // }
+    /// Returns the numeric names of the directories in `folder`, which is missing in output
+    /// directories of older versions.
+    fn get_ids(&self, folder: &str) -> ProFuzzResult<Vec<usize>> {
+        let folder = self.path.join(folder);
+        if !folder.is_dir() {
+            return Ok(vec![]);
+        }
+        let mut ids = vec![];
+        for entry in std::fs::read_dir(folder)? {
+            let entry = entry?;
+            if entry.path().is_dir()
+                && let Some(id) = entry.file_name().to_str().and_then(|n| n.parse().ok())
+            {
+                ids.push(id);
+            }
+        }
+        ids.sort_unstable();
+        Ok(ids)
+    }
+
+    pub(crate) fn get_crash_artifacts_folder(&self, id: usize) -> ProFuzzResult<PathBuf> {
+        let dir = self.get_crash_dir(id).join("artifacts");
+        std::fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
+
+    pub(crate) fn get_queue_file(&self) -> PathBuf {
+        self.path.join("queue.json")
+    }
+
+    /// Writes the content into a temporary file first and then renames it, so an interrupted
+    /// write does not destroy the previous state.
+    pub(crate) fn write_atomic(path: &Path, content: &[u8]) -> ProFuzzResult<()> {
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, content)?;
+        std::fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    pub(crate) fn get_stats_file(&self) -> PathBuf {
+        self.path.join("stats.json")
+    }
+
+    pub(crate) fn get_rng_file(&self) -> PathBuf {
+        self.path.join("rng.json")
+    }
+
+    pub(crate) fn get_target_file(&self) -> PathBuf {
+        self.path.join("target.json")
+    }
+
+    pub(crate) fn get_clusters_file(&self) -> PathBuf {
+        self.path.join("clusters.json")
+    }
 }
@@ -0,0 +1,156 @@
+use crate::error::ProFuzzError;
+use crate::fuzz::stats::Stats;
+use crate::mutator::Mutator;
+use crate::output::Output;
+use crate::traits::{Corpus, HealthCheck, Mutable, ResetHandler, Transport};
+use crate::triage::replay::Replayer;
+use serde_json::{Value, json};
+use std::sync::{Arc, Mutex};
+
+/// Output directory in the temp directory which is removed when dropped.
+pub(crate) struct TempOutput {
+    pub(crate) output: Output,
+}
+
+impl TempOutput {
+    /// Creates the output directory `profuzz-<name>-<pid>` with the given crashes stored in the
+    /// legacy `crashes.json`, which is migrated into crash directories by `Output::init`.
+    pub(crate) fn with_legacy_crashes(name: &str, crashes: &[Value]) -> Self {
+        let dir = std::env::temp_dir().join(format!("profuzz-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("create output dir");
+        std::fs::write(dir.join("crashes.json"), Value::from(crashes).to_string())
+            .expect("write crashes");
+        let output = Output::init(dir, true).expect("init output");
+        Self { output }
+    }
+}
+
+impl Drop for TempOutput {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(self.output.get_path());
+    }
+}
+
+/// A crash of the legacy `crashes.json`, the last sent buffers are ordered newest first.
+pub(crate) fn legacy_crash(buffer: &[u8], last_send_buffers: &[&[u8]], worker: usize) -> Value {
+    json!({
+        "buffer": buffer,
+        "last_send_buffers": last_send_buffers,
+        "stats": Stats::default(),
+        "worker": worker,
+    })
+}
+
+/// Target which crashes when it receives a buffer for which `crashes` returns true and stays
+/// crashed until it is reset. It acts as transport, health check and reset handler at once.
+#[derive(Clone)]
+pub(crate) struct FakeTarget {
+    crashes: fn(&[u8]) -> bool,
+    crashed: Arc<Mutex<bool>>,
+}
+
+impl FakeTarget {
+    pub(crate) fn new(crashes: fn(&[u8]) -> bool) -> Self {
+        Self {
+            crashes,
+            crashed: Arc::default(),
+        }
+    }
+
+    pub(crate) fn replayer(crashes: fn(&[u8]) -> bool) -> Replayer<Self, Self, Self> {
+        let target = Self::new(crashes);
+        Replayer::new(target.clone(), target.clone(), target)
+    }
+
+    fn set_crashed(&self, crashed: bool) {
+        *self.crashed.lock().expect("lock fake target") = crashed;
+    }
+}
+
+impl Transport for FakeTarget {
+    fn title(&self) -> String {
+        "fake".to_string()
+    }
+
+    async fn connect(&mut self) -> Result<(), ProFuzzError> {
+        Ok(())
+    }
+
+    async fn close(&mut self) -> Result<(), ProFuzzError> {
+        Ok(())
+    }
+
+    async fn read(&mut self, _buf: &mut [u8]) -> Result<usize, ProFuzzError> {
+        Ok(0)
+    }
+
+    async fn write(&mut self, buf: &[u8]) -> Result<(), ProFuzzError> {
+        if (self.crashes)(buf) {
+            self.set_crashed(true);
+        }
+        Ok(())
+    }
+}
+
+impl HealthCheck for FakeTarget {
+    async fn is_ok(&mut self) -> Result<bool, ProFuzzError> {
+        Ok(!*self.crashed.lock().expect("lock fake target"))
+    }
+}
+
+impl ResetHandler for FakeTarget {
+    async fn reset(&mut self) -> Result<(), ProFuzzError> {
+        self.set_crashed(false);
+        Ok(())
+    }
+}
+
+/// Corpus of a kind, a value and a payload. A value of 0x80 or more is only valid for kinds
+/// other than 0.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Message(pub(crate) Vec<u8>);
+
+impl Corpus for Message {
+    fn from_bytes(buf: Vec<u8>) -> Option<Self> {
+        (buf.len() >= 2 && (buf[0] != 0 || buf[1] < 0x80)).then_some(Self(buf))
+    }
+    fn to_bytes(self) -> Vec<u8> {
+        self.0
+    }
+    fn build(self) -> Vec<u8> {
+        self.0
+    }
+    fn show(&self) -> String {
+        format!("{:?}", self.0)
+    }
+    fn fields(&self) -> Vec<(String, String)> {
+        vec![
+            ("kind".to_string(), self.0[0].to_string()),
+            ("value".to_string(), self.0[1].to_string()),
+            ("payload".to_string(), hex::encode(&self.0[2..])),
+        ]
+    }
+    fn copy_field(&mut self, name: &str, other: &Self) -> bool {
+        match name {
+            "kind" => self.0[0] = other.0[0],
+            "value" => self.0[1] = other.0[1],
+            "payload" => {
+                self.0.truncate(2);
+                self.0.extend_from_slice(&other.0[2..]);
+            }
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl Mutable for Message {
+    fn mutate<R: rand::Rng>(&mut self, mutator: &mut Mutator<R>) {
+        for byte in &mut self.0 {
+            if mutator.gen_chance(0.5) {
+                mutator.mutate(byte);
+            }
+        }
+    }
+}
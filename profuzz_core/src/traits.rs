@@ -1,5 +1,7 @@
 use crate::error::ProFuzzError;
+use crate::fuzz::control::CrashInfo;
 use crate::mutator::Mutator;
+use serde::{Deserialize, Serialize};
 
 /// Convert the Mutable corpus to `Vec<u8>` or converts `Vec<u8>` to the Mutable
 pub trait Corpus
@@ -14,6 +16,19 @@ where
     fn build(self) -> Vec<u8>;
     /// Returns a human readable representation of the corpus
     fn show(&self) -> String;
+
+    /// Returns the name and the human readable value of every field of the corpus, e.g.
+    /// `("function_id", "3")`. The triage and the minimization revert fields which differ from a
+    /// non-crashing corpus with `copy_field`. The default implementation has no fields.
+    fn fields(&self) -> Vec<(String, String)> {
+        vec![]
+    }
+
+    /// Sets the field `name` to its value in `other`. Returns false if one of the corpora does
+    /// not have the field.
+    fn copy_field(&mut self, _name: &str, _other: &Self) -> bool {
+        false
+    }
 }
 
 /// Transport layer to connect to the target
@@ -41,6 +56,45 @@ pub trait Transport {
     /// Write data to the target.
     /// # Errors
     fn write(&mut self, buf: &[u8]) -> impl std::future::Future<Output = Result<(), ProFuzzError>>;
+
+    /// Describes how the transport connects to the target. It is stored in `target.json` of the
+    /// output directory, so a crash can be reproduced without the fuzzer, e.g. with the proof of
+    /// concept generated by the `poc` command.
+    fn metadata(&self) -> TransportMetadata {
+        TransportMetadata::Unknown
+    }
+}
+
+/// Connection parameters of a `Transport`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TransportMetadata {
+    /// TCP client which connects for every buffer
+    Tcp {
+        /// Address of the target, e.g. `127.0.0.1:8080`
+        address: String,
+        /// Read timeout in millis, `None` if responses are not read
+        read_timeout: Option<u64>,
+        /// Write timeout in millis
+        write_timeout: u64,
+        /// Messages which are sent after every connect, each followed by a read
+        #[serde(default)]
+        send_after_connected: Vec<Vec<u8>>,
+    },
+    /// UDP client sending one datagram per buffer
+    Udp {
+        /// Address of the target, e.g. `127.0.0.1:5353`
+        address: String,
+        /// Read timeout in millis, `None` if responses are not read
+        read_timeout: Option<u64>,
+    },
+    /// Raw ethernet frames sent on a network interface
+    RawEthernet {
+        /// Name of the network interface, e.g. `eth0`
+        interface: String,
+    },
+    /// The transport does not provide its connection parameters
+    Unknown,
 }
 
 /// The reset is called from the protocol fuzzer in case the healthcheck returns the target is not
@@ -62,3 +116,34 @@ pub trait Mutable {
     /// Mutate is called by the core to mutate the corpus files
     fn mutate<R: rand::Rng>(&mut self, mutator: &mut Mutator<R>);
 }
+
+/// Observer which is notified by the fuzzing engine about its findings, e.g. to push them into an
+/// issue tracker. All functions have an empty default implementation, so only the required ones
+/// have to be implemented. The functions are called from the fuzzing loop, so they should return
+/// quickly.
+pub trait FuzzObserver: Send {
+    /// Called when a corpus produced a unique response and was added to the queue
+    fn on_new_response(&mut self, _worker: usize, _corpus: &[u8]) {}
+
+    /// Called when the health check failed and the crash was stored in the output directory
+    fn on_crash(&mut self, _crash: &CrashInfo) {}
+
+    /// Called before the `ResetHandler` of a worker is triggered
+    fn on_reset(&mut self, _worker: usize) {}
+
+    /// Called when all queue elements were used as mutation root
+    fn on_cycle_done(&mut self, _cycles: usize) {}
+}
+
+/// Collects artifacts of a crash, e.g. the serial log of the target, before the `ResetHandler`
+/// is triggered. The artifacts are stored next to the crash in the output directory. The
+/// collectors are called on the blocking thread pool of the tokio runtime, so `collect` may
+/// block.
+pub trait CrashCollector: Send {
+    /// Name of the collector, used as file name of the stored artifact
+    fn name(&self) -> String;
+
+    /// Returns the artifact for the given crash
+    /// # Errors
+    fn collect(&mut self, crash: &CrashInfo) -> Result<Vec<u8>, ProFuzzError>;
+}
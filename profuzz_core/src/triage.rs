@@ -1,3 +1,16 @@
+mod ddmin;
+/// Offline clustering of crashes caused by the same bug
+pub mod dedup;
 /// Dynamic approach for identifying the crash
 pub mod dynamic;
+/// SARIF and `JUnit` export of the findings
+pub mod export;
+mod isolate;
+/// Minimization of the buffer crashing the target
+pub mod minimize;
+/// Proof of concept generation for the disclosure of crashes
+pub mod poc;
+pub(crate) mod replay;
+/// Human-readable reports of the crashes
+pub mod report;
 mod utils;
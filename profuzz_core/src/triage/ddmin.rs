@@ -0,0 +1,90 @@
+use crate::error::ProFuzzResult;
+
+/// Minimizes the sequence with the ddmin algorithm, so that `crashes` still returns true for
+/// the result while removing any single chunk of it does not crash anymore. The order of the
+/// elements is kept. The given sequence must crash.
+pub(crate) async fn ddmin<T, F>(mut sequence: Vec<T>, mut crashes: F) -> ProFuzzResult<Vec<T>>
+where
+    T: Clone,
+    F: AsyncFnMut(&[T]) -> ProFuzzResult<bool>,
+{
+    let mut granularity = 2;
+    while sequence.len() >= 2 {
+        let chunks = split(sequence.len(), granularity);
+        let mut reduced = false;
+
+        // first try to find a single chunk crashing the target
+        for range in &chunks {
+            let subset = sequence[range.clone()].to_vec();
+            if crashes(&subset).await? {
+                sequence = subset;
+                granularity = 2;
+                reduced = true;
+                break;
+            }
+        }
+
+        // then try to remove a single chunk
+        if !reduced && granularity > 2 {
+            for range in &chunks {
+                let mut complement = sequence[..range.start].to_vec();
+                complement.extend_from_slice(&sequence[range.end..]);
+                if crashes(&complement).await? {
+                    sequence = complement;
+                    granularity = (granularity - 1).max(2);
+                    reduced = true;
+                    break;
+                }
+            }
+        }
+
+        if !reduced {
+            if granularity >= sequence.len() {
+                break;
+            }
+            granularity = (granularity * 2).min(sequence.len());
+        }
+        tracing::info!("Reduced the sequence to {} buffers.", sequence.len());
+    }
+    Ok(sequence)
+}
+
+/// Splits `len` elements into `n` nearly equal ranges.
+fn split(len: usize, n: usize) -> Vec<std::ops::Range<usize>> {
+    let mut ranges = Vec::with_capacity(n);
+    let mut start = 0;
+    for i in 0..n {
+        let end = start + (len - start) / (n - i);
+        ranges.push(start..end);
+        start = end;
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minimize(len: usize, needed: &[usize]) -> (Vec<usize>, usize) {
+        let mut trials = 0;
+        let sequence = (0..len).collect();
+        let result = futures::executor::block_on(ddmin(sequence, async |candidate: &[usize]| {
+            trials += 1;
+            Ok(needed.iter().all(|n| candidate.contains(n)))
+        }));
+        (result.unwrap_or_default(), trials)
+    }
+
+    #[test]
+    fn finds_a_single_buffer() {
+        let (result, trials) = minimize(1000, &[723]);
+        assert_eq!(result, vec![723]);
+        assert!(trials < 50);
+    }
+
+    #[test]
+    fn finds_a_sequence() {
+        assert_eq!(minimize(1000, &[17, 500, 999]).0, vec![17, 500, 999]);
+        assert_eq!(minimize(7, &[2, 3]).0, vec![2, 3]);
+    }
+}
@@ -0,0 +1,357 @@
+use crate::error::ProFuzzResult;
+use crate::fuzz::stats::Stats;
+use crate::output::Output;
+use crate::traits::Corpus;
+use crate::types::{Crash, load_reproducer};
+use chrono::TimeDelta;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Default minimal similarity of the `Corpus::show` lines of two crashes in the same cluster
+pub const DEFAULT_THRESHOLD: f64 = 0.8;
+
+/// Failure signature of a health check which returned false
+pub(crate) const UNHEALTHY: &str = "unhealthy";
+
+/// Features of a crash which are available without the target.
+pub(crate) struct CrashFeatures {
+    /// Lines of `Corpus::show` of the buffer which triggered the crash
+    lines: HashSet<String>,
+    /// How the health check failed, e.g. the error message of the health check
+    failure: Option<String>,
+    /// Time from sending the triggering buffer until the crash was detected, only known if the
+    /// health check ran right after the buffer was sent
+    latency: Option<TimeDelta>,
+}
+
+impl CrashFeatures {
+    pub(crate) fn new<M: Corpus>(
+        buffer: &[u8],
+        failure: Option<String>,
+        latency: Option<TimeDelta>,
+    ) -> Self {
+        let show = M::from_bytes(buffer.to_vec()).map_or_else(|| hex::encode(buffer), |c| c.show());
+        Self {
+            lines: show.lines().map(str::to_string).collect(),
+            failure,
+            latency,
+        }
+    }
+
+    /// Uses the stored metadata and the buffer of the crash.
+    pub(crate) fn from_crash<M: Corpus>(crash: &Crash, buffer: &[u8]) -> Self {
+        let latency = match (crash.detected_at, crash.sent_at) {
+            (Some(detected_at), Some(sent_at)) if crash.send_failed => Some(detected_at - sent_at),
+            _ => None,
+        };
+        Self::new::<M>(buffer, crash.healthcheck_failure.clone(), latency)
+    }
+
+    /// Jaccard similarity of the `Corpus::show` lines.
+    #[allow(clippy::cast_precision_loss)]
+    fn similarity(&self, other: &CrashFeatures) -> f64 {
+        let union = self.lines.union(&other.lines).count();
+        if union == 0 {
+            return 1.0;
+        }
+        self.lines.intersection(&other.lines).count() as f64 / union as f64
+    }
+
+    /// Unknown features of older crashes match everything.
+    fn matches(&self, other: &CrashFeatures, threshold: f64) -> bool {
+        let same_failure = match (&self.failure, &other.failure) {
+            (Some(a), Some(b)) => a == b,
+            _ => true,
+        };
+        let similar_latency = match (self.latency, other.latency) {
+            (Some(a), Some(b)) => latency_bucket(a).abs_diff(latency_bucket(b)) <= 1,
+            _ => true,
+        };
+        same_failure && similar_latency && self.similarity(other) >= threshold
+    }
+}
+
+/// Orders of magnitude (base 2) of the latency in milliseconds, so a crash detected immediately
+/// is distinguished from a target which slowly degrades.
+fn latency_bucket(latency: TimeDelta) -> u32 {
+    u64::try_from(latency.num_milliseconds())
+        .unwrap_or(0)
+        .saturating_add(1)
+        .ilog2()
+}
+
+/// Groups crashes which are likely caused by the same bug. Every crash is added to the first
+/// cluster whose first crash has similar features.
+pub(crate) struct CrashClusters {
+    threshold: f64,
+    clusters: Vec<Cluster>,
+}
+
+struct Cluster {
+    /// Features of the first crash of the cluster
+    leader: CrashFeatures,
+    representative: usize,
+    crashes: Vec<usize>,
+}
+
+/// Content of `clusters.json`, the threshold is stored so a resumed session continues with the
+/// threshold of the last `dedup`.
+#[derive(Serialize, Deserialize)]
+struct SavedClusters {
+    threshold: f64,
+    clusters: Vec<CrashCluster>,
+}
+
+impl CrashClusters {
+    pub(crate) fn new(threshold: f64) -> Self {
+        Self {
+            threshold,
+            clusters: vec![],
+        }
+    }
+
+    /// Adds the crash to a cluster and returns the index of the cluster and true if a new
+    /// cluster was created.
+    pub(crate) fn insert(&mut self, id: usize, features: CrashFeatures) -> (usize, bool) {
+        if let Some(index) = self
+            .clusters
+            .iter()
+            .position(|cluster| cluster.leader.matches(&features, self.threshold))
+        {
+            self.clusters[index].crashes.push(id);
+            return (index, false);
+        }
+        self.clusters.push(Cluster {
+            leader: features,
+            representative: id,
+            crashes: vec![id],
+        });
+        (self.clusters.len() - 1, true)
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.clusters.len()
+    }
+
+    /// Loads the clusters from `clusters.json`, so the clusters and the threshold of the last
+    /// `dedup` are kept. Crashes which are not part of a cluster yet, e.g. of an output
+    /// directory of an older version, are added to the clusters.
+    pub(crate) fn load<M: Corpus>(output: &Output) -> ProFuzzResult<Self> {
+        let ids = output.get_crash_ids()?;
+        let mut clusters = Self::new(DEFAULT_THRESHOLD);
+        let path = output.get_clusters_file();
+        if path.is_file() {
+            let saved: SavedClusters = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+            clusters.threshold = saved.threshold;
+            for mut cluster in saved.clusters {
+                // crashes could have been removed by the user
+                cluster.crashes.retain(|id| ids.contains(id));
+                let Some(&leader) = cluster.crashes.first() else {
+                    continue;
+                };
+                let (crash, reproducer) = load_crash(output, leader)?;
+                let buffer = reproducer.as_deref().unwrap_or(&crash.buffer);
+                if !cluster.crashes.contains(&cluster.representative) {
+                    cluster.representative = leader;
+                }
+                clusters.clusters.push(Cluster {
+                    leader: CrashFeatures::from_crash::<M>(&crash, buffer),
+                    representative: cluster.representative,
+                    crashes: cluster.crashes,
+                });
+            }
+        }
+        for id in ids {
+            if clusters.clusters.iter().any(|c| c.crashes.contains(&id)) {
+                continue;
+            }
+            let (mut crash, reproducer) = load_crash(output, id)?;
+            let buffer = reproducer.as_deref().unwrap_or(&crash.buffer);
+            let (cluster, _) = clusters.insert(id, CrashFeatures::from_crash::<M>(&crash, buffer));
+            crash.cluster = Some(cluster);
+            crash.save_meta(output)?;
+        }
+        Ok(clusters)
+    }
+
+    /// Stores the clusters in `clusters.json`.
+    pub(crate) fn save(&self, output: &Output) -> ProFuzzResult<()> {
+        let saved = SavedClusters {
+            threshold: self.threshold,
+            clusters: self.to_vec(),
+        };
+        let content = serde_json::to_string_pretty(&saved)?;
+        Output::write_atomic(&output.get_clusters_file(), content.as_bytes())
+    }
+
+    fn to_vec(&self) -> Vec<CrashCluster> {
+        self.clusters
+            .iter()
+            .enumerate()
+            .map(|(id, cluster)| CrashCluster {
+                id,
+                representative: cluster.representative,
+                crashes: cluster.crashes.clone(),
+                failure: cluster.leader.failure.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Loads the metadata of the crash and the last buffer of its reproducer, which is used
+/// instead of the last sent buffer for triaged crashes.
+fn load_crash(output: &Output, id: usize) -> ProFuzzResult<(Crash, Option<Vec<u8>>)> {
+    let crash = Crash::load_meta(output, id)?;
+    let reproducer =
+        load_reproducer(&output.get_crash_dir(id))?.and_then(|mut sequence| sequence.pop());
+    Ok((crash, reproducer))
+}
+
+/// A group of crashes which are likely caused by the same bug, stored in `clusters.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashCluster {
+    /// Index of the cluster
+    pub id: usize,
+    /// Crash representing the cluster, preferring triaged crashes and short buffers
+    pub representative: usize,
+    /// All crashes of the cluster
+    pub crashes: Vec<usize>,
+    /// How the health check failed for the crashes of this cluster
+    pub failure: Option<String>,
+}
+
+/// Clusters all crashes of the output directory again without the target by the structural
+/// similarity of their triggering buffers, the failure of the health check and the time until
+/// the crash was detected. The buffer of the reproducer is used for triaged crashes. The
+/// clusters are stored in `clusters.json`, which replaces the clusters of the fuzzer, the
+/// cluster of every crash in its `crash.json` and the number of clusters is used as crash
+/// counter in `stats.json`.
+/// # Errors
+pub fn dedup_from_output_dir<M: Corpus>(
+    output: &Output,
+    threshold: f64,
+) -> ProFuzzResult<Vec<CrashCluster>> {
+    let mut clusters = CrashClusters::new(threshold);
+    let mut preference = HashMap::new();
+    for id in output.get_crash_ids()? {
+        let (mut crash, reproducer) = load_crash(output, id)?;
+        let buffer = reproducer.as_deref().unwrap_or(&crash.buffer);
+        let (cluster, _) = clusters.insert(id, CrashFeatures::from_crash::<M>(&crash, buffer));
+        preference.insert(id, (reproducer.is_none(), buffer.len(), id));
+        crash.cluster = Some(cluster);
+        crash.save_meta(output)?;
+    }
+    for cluster in &mut clusters.clusters {
+        if let Some(&representative) = cluster.crashes.iter().min_by_key(|id| preference.get(*id)) {
+            cluster.representative = representative;
+        }
+    }
+
+    clusters.save(output)?;
+    if let Some(mut stats) = Stats::load(output)? {
+        stats.total_crashes = clusters.len();
+        stats.save(output)?;
+    }
+    Ok(clusters.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{TempOutput, legacy_crash};
+
+    /// Shows every byte in its own line
+    struct Bytes(Vec<u8>);
+
+    impl Corpus for Bytes {
+        fn from_bytes(buf: Vec<u8>) -> Option<Self> {
+            Some(Self(buf))
+        }
+        fn to_bytes(self) -> Vec<u8> {
+            self.0
+        }
+        fn build(self) -> Vec<u8> {
+            self.0
+        }
+        fn show(&self) -> String {
+            let lines: Vec<String> = self
+                .0
+                .iter()
+                .enumerate()
+                .map(|(i, b)| format!("{i}: {b}"))
+                .collect();
+            lines.join("\n")
+        }
+    }
+
+    fn features(lines: &[&str], failure: &str, latency_ms: i64) -> CrashFeatures {
+        CrashFeatures {
+            lines: lines.iter().map(|line| (*line).to_string()).collect(),
+            failure: Some(failure.to_string()),
+            latency: Some(TimeDelta::milliseconds(latency_ms)),
+        }
+    }
+
+    #[test]
+    fn clusters_similar_crashes() {
+        let base = [
+            "a: 1", "b: 2", "c: 3", "d: 4", "e: 5", "f: 6", "g: 7", "h: 8", "i: 9",
+        ];
+        let mut similar = base;
+        similar[8] = "i: 10";
+        let mut different = base;
+        different[..4].copy_from_slice(&["a: 9", "b: 9", "c: 9", "d: 9"]);
+
+        let mut clusters = CrashClusters::new(DEFAULT_THRESHOLD);
+        assert_eq!(
+            clusters.insert(0, features(&base, UNHEALTHY, 10)),
+            (0, true)
+        );
+        assert_eq!(
+            clusters.insert(1, features(&similar, UNHEALTHY, 12)),
+            (0, false)
+        );
+        assert_eq!(
+            clusters.insert(2, features(&different, UNHEALTHY, 10)),
+            (1, true)
+        );
+        assert_eq!(
+            clusters.insert(3, features(&base, "timeout", 10)),
+            (2, true)
+        );
+        assert_eq!(
+            clusters.insert(4, features(&base, UNHEALTHY, 5000)),
+            (3, true)
+        );
+        assert_eq!(clusters.len(), 4);
+    }
+
+    #[test]
+    fn resumes_with_the_clusters_of_dedup() {
+        let similar = [1, 2, 3, 4, 5, 6, 7, 8, 9, 11];
+        let temp = TempOutput::with_legacy_crashes(
+            "dedup",
+            &[
+                legacy_crash(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10], &[], 0),
+                legacy_crash(&similar, &[], 0),
+                legacy_crash(&[9; 10], &[], 0),
+            ],
+        );
+        let output = &temp.output;
+
+        // 9 of 11 lines are shared, so the first two crashes are only similar below 0.82
+        let clusters = CrashClusters::load::<Bytes>(output).expect("load clusters");
+        assert_eq!(clusters.len(), 2);
+        let clusters = dedup_from_output_dir::<Bytes>(output, 0.9).expect("dedup");
+        assert_eq!(clusters.len(), 3);
+
+        let mut clusters = CrashClusters::load::<Bytes>(output).expect("load clusters");
+        assert_eq!(clusters.len(), 3);
+        let features = CrashFeatures::new::<Bytes>(&similar, None, None);
+        assert_eq!(clusters.insert(3, features), (1, false));
+        assert_eq!(
+            Crash::load_meta(output, 1).expect("load crash").cluster,
+            Some(1)
+        );
+    }
+}
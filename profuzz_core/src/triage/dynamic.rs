@@ -1,24 +1,31 @@
 use crate::error::{ProFuzzError, ProFuzzResult};
 use crate::output::Output;
 use crate::traits::{Corpus, HealthCheck, Mutable, ResetHandler, Transport};
-use crate::triage::utils::{hamming_distance, mark_differences};
-use crate::types::Crash;
-use sha1::{Digest, Sha1};
-use std::time::Duration;
-use tokio::time::sleep;
+use crate::triage::ddmin::ddmin;
+use crate::triage::isolate::{differing_fields, isolate_fields, revert};
+use crate::triage::replay::Replayer;
+use crate::triage::utils::{closest_any_buffer, mark_differences};
+use crate::types::{Crash, load_reproducer, save_reproducer};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 
 /// Dynamic approach for identifying the crash cause.
 /// - This does iterate over all corpus files send since the last successfull health check
 /// - Everytime a single corpus is send a healthcheck is performed to determine if this was the cause
+/// - With `TriageMode::Ddmin` the history is instead shrunk to a minimal crashing sequence
+/// - With `set_isolate` the fields of the `Corpus` which differ from the closest non-crashing
+///   buffer are reverted on the target to identify the fields necessary for the crash
+/// - With `set_repeat` the reproducer is replayed multiple times to detect flaky crashes
 pub struct DynamicTriage<T, H, R>
 where
     T: Transport,
     H: HealthCheck,
     R: ResetHandler,
 {
-    transport: T,
-    healthcheck: H,
-    resethandler: R,
+    target: Replayer<T, H, R>,
+    mode: TriageMode,
+    isolate: bool,
+    repeat: Option<usize>,
 }
 
 impl<T, H, R> DynamicTriage<T, H, R>
@@ -30,173 +37,564 @@ where
     /// creates a new instance of the dynamic triaging
     pub fn new(transport: T, healthcheck: H, resethandler: R) -> Self {
         Self {
-            transport,
-            healthcheck,
-            resethandler,
+            target: Replayer::new(transport, healthcheck, resethandler),
+            mode: TriageMode::Linear,
+            isolate: false,
+            repeat: None,
         }
     }
 
+    /// Sets the approach used to identify the crash cause
+    pub fn set_mode(&mut self, mode: TriageMode) {
+        self.mode = mode;
+    }
+
+    /// Enables the isolation of the fields causing the crash, only used by `TriageMode::Linear`
+    pub fn set_isolate(&mut self, isolate: bool) {
+        self.isolate = isolate;
+    }
+
+    /// Replays the reproducer of every crash `repeat` times after the triage, resetting the
+    /// target before every replay, to detect if the crash is reliable or flaky
+    pub fn set_repeat(&mut self, repeat: Option<usize>) {
+        self.repeat = repeat.filter(|repeat| *repeat > 0);
+    }
+
     /// loads the crash informations for the output dir and tries to identiy the single input
     /// # Errors
     pub async fn triage_from_output_dir<M: Mutable + Corpus + Clone>(
         &mut self,
         output: &Output,
-    ) -> ProFuzzResult<()> {
-        let crash_file = output.get_crash_file();
-        let content = std::fs::read_to_string(crash_file)?;
-        let crashes: Vec<Crash> = serde_json::from_str(&content)?;
-        self.triage::<M>(crashes, output).await?;
-        Ok(())
+        selection: TriageSelection,
+    ) -> ProFuzzResult<Vec<TriageResult>> {
+        let crashes = if let TriageSelection::Crash(id) = selection {
+            if !output.get_crash_dir(id).is_dir() {
+                return Err(ProFuzzError::Custom {
+                    err_msg: format!("Crash {id} does not exist."),
+                });
+            }
+            vec![Crash::load(output, id)?]
+        } else {
+            Crash::load_all(output)?
+        };
+        self.triage::<M>(crashes, output, selection).await
+    }
+
+    /// Replays the reproducer of every stored crash without asking and checks if it still
+    /// crashes the target, e.g. after a fix of the target was deployed. The target is reset
+    /// before every replay.
+    /// # Errors
+    pub async fn regress_from_output_dir(
+        &mut self,
+        output: &Output,
+    ) -> ProFuzzResult<Vec<RegressionResult>> {
+        let mut results = vec![];
+        let mut crashed = false;
+        for id in output.get_crash_ids()? {
+            let crash = Crash::load_meta(output, id)?;
+            let mut result = RegressionResult {
+                crash_id: id,
+                target: crash.target,
+                buffers: 0,
+                status: RegressionStatus::Skipped,
+            };
+            if let Some(sequence) = load_reproducer(&output.get_crash_dir(id))? {
+                tracing::info!("Replaying the reproducer of crash {id}.");
+                result.buffers = sequence.len();
+                crashed = self.target.replay(&sequence).await?;
+                result.status = if crashed {
+                    RegressionStatus::Failed
+                } else {
+                    RegressionStatus::Passed
+                };
+            } else {
+                tracing::warn!("Crash {id} has no reproducer, triage it first.");
+            }
+            results.push(result);
+        }
+        if crashed {
+            self.target.reset().await?;
+            self.target.wait_until_healthy().await;
+        }
+        Ok(results)
     }
 
     pub(crate) async fn triage<M>(
         &mut self,
         crashes: Vec<Crash>,
         output: &Output,
-    ) -> ProFuzzResult<()>
+        selection: TriageSelection,
+    ) -> ProFuzzResult<Vec<TriageResult>>
     where
         M: Mutable + Corpus + Clone,
     {
+        let mut results = vec![];
         let len = crashes.len();
         for (i, crash) in crashes.into_iter().enumerate() {
-            println!("Triage {}/{len} [y/n]? ", i + 1);
-            let mut input = String::new();
-
-            std::io::stdin()
-                .read_line(&mut input)
-                .expect("Failed to read line");
+            if selection == TriageSelection::Interactive {
+                println!("Triage {}/{len} [y/n]? ", i + 1);
+                let mut input = String::new();
 
-            let input = input.trim();
+                std::io::stdin()
+                    .read_line(&mut input)
+                    .expect("Failed to read line");
 
-            if input == "y" {
-                tracing::info!("Starting with detecting the cause.");
-                if !self.find_the_crash_cause::<M>(crash, output).await? {
-                    tracing::error!("Could not detect the crash :/");
+                if input.trim() != "y" {
+                    continue;
                 }
             }
+            tracing::info!("Starting with detecting the cause of crash {}.", crash.id);
+            let last_buffer = crash.buffer.clone();
+            let mut result = match self.mode {
+                TriageMode::Linear => self.find_the_crash_cause::<M>(crash, output).await?,
+                TriageMode::Ddmin => self.find_the_crash_sequence(crash, output).await?,
+            };
+            if !result.reproduced {
+                tracing::error!("Could not detect the crash :/");
+            }
+            if let Some(repeat) = self.repeat {
+                let sequence = match load_reproducer(&output.get_crash_dir(result.crash_id))? {
+                    Some(sequence) if result.reproduced => sequence,
+                    _ => vec![last_buffer],
+                };
+                let reproducibility = self.score(&sequence, repeat).await?;
+                let mut crash = Crash::load_meta(output, result.crash_id)?;
+                crash.reproducibility = Some(reproducibility.clone());
+                crash.save_meta(output)?;
+                result.reproducibility = Some(reproducibility);
+            }
+            result.save(output)?;
+            results.push(result);
         }
-        Ok(())
+        Ok(results)
     }
 
-    /// Iterates over all `last_send_buffers` items and checking after every send if the target
+    /// Iterates over all `history` items and checking after every send if the target
     /// crashed.
     async fn find_the_crash_cause<M>(
         &mut self,
-        mut crash: Crash,
+        crash: Crash,
         output: &Output,
-    ) -> ProFuzzResult<bool>
+    ) -> ProFuzzResult<TriageResult>
     where
         M: Mutable + Corpus + Clone,
     {
         tracing::info!("Starting with a healthcheck.");
-        if self
-            .send_and_detect_crash::<M>(&crash.buffer, output, &crash.last_send_buffers)
+        let crash_dir = output.get_crash_dir(crash.id);
+        let history: Vec<Vec<u8>> = crash.history.into_iter().map(|b| b.buffer).collect();
+        let mut result = TriageResult::new(crash.id);
+        if let Some(reproduced) = self
+            .send_and_detect_crash::<M>(&crash.buffer, &history)
             .await?
         {
-            return Ok(true);
+            self.isolate_fields::<M>(&crash.buffer, &mut result, &reproduced)
+                .await?;
+            result.set_reproducer(&crash_dir, &[crash.buffer], reproduced)?;
+            return Ok(result);
         }
         tracing::info!("Running full test of all buffers.");
 
         let mut similar_corpuses = vec![];
-        let total_len = crash.last_send_buffers.len();
-        crash.last_send_buffers.reverse();
-        for (i, crash_buffer) in crash.last_send_buffers.into_iter().enumerate() {
-            print!("\r Testing {i}/{total_len}");
-            if self
-                .send_and_detect_crash::<M>(&crash_buffer, output, &similar_corpuses)
+        let total_len = history.len();
+        for (i, crash_buffer) in history.into_iter().enumerate() {
+            eprint!("\r Testing {i}/{total_len}");
+            if let Some(reproduced) = self
+                .send_and_detect_crash::<M>(&crash_buffer, &similar_corpuses)
                 .await?
             {
                 tracing::info!("Identified the corpus that crashed the target.");
-                return Ok(true);
+                self.isolate_fields::<M>(&crash_buffer, &mut result, &reproduced)
+                    .await?;
+                result.set_reproducer(&crash_dir, &[crash_buffer], reproduced)?;
+                return Ok(result);
             }
             similar_corpuses.push(crash_buffer);
         }
-        Ok(false)
+        Ok(result)
+    }
+
+    /// Identifies the fields of the crashing buffer which are necessary for the crash by
+    /// reverting them to the values of the closest non-crashing buffer.
+    async fn isolate_fields<M>(
+        &mut self,
+        buffer: &[u8],
+        result: &mut TriageResult,
+        reproduced: &Reproduced,
+    ) -> ProFuzzResult<()>
+    where
+        M: Corpus + Clone,
+    {
+        if !self.isolate {
+            return Ok(());
+        }
+        let Some(base) = &reproduced.closest_non_crashing else {
+            tracing::warn!("No non-crashing buffer to isolate the crashing fields.");
+            return Ok(());
+        };
+        let (Some(crash), Some(base)) =
+            (M::from_bytes(buffer.to_vec()), M::from_bytes(base.clone()))
+        else {
+            tracing::warn!("Could not parse the buffers to isolate the crashing fields.");
+            return Ok(());
+        };
+        if crash.fields().is_empty() {
+            tracing::warn!("The corpus has no fields to isolate, see `Corpus::fields`.");
+            return Ok(());
+        }
+        tracing::info!("Isolating the fields causing the crash.");
+        let isolation = isolate_fields(&mut self.target, &crash, &base).await?;
+        let optional: Vec<String> = differing_fields(&crash, &base)
+            .into_iter()
+            .filter(|field| {
+                !isolation.necessary.contains(field) && !isolation.undetermined.contains(field)
+            })
+            .collect();
+        let optional: Vec<&String> = optional.iter().collect();
+        if let Some(isolated) = revert(&crash, &base, &optional) {
+            let (shown, base_shown) = (isolated.show(), base.show());
+            eprintln!("{}", mark_differences(&shown, &base_shown));
+            let base_lines: Vec<&str> = base_shown.lines().collect();
+            result.isolated_lines = shown
+                .lines()
+                .enumerate()
+                .filter(|(i, line)| base_lines.get(*i) != Some(line))
+                .map(|(_, line)| line.to_string())
+                .collect();
+            result.isolated = Some(hex::encode(isolated.build()));
+        }
+        result.isolated_fields = IsolatedField::from_names(&isolation.necessary, &crash, &base);
+        result.undetermined_fields =
+            IsolatedField::from_names(&isolation.undetermined, &crash, &base);
+        Ok(())
+    }
+
+    /// Replays the sequence `repeat` times and measures how often and how fast it crashes the
+    /// target.
+    async fn score(
+        &mut self,
+        sequence: &[Vec<u8>],
+        repeat: usize,
+    ) -> ProFuzzResult<Reproducibility> {
+        let mut time_to_fail_secs = vec![];
+        for i in 0..repeat {
+            eprint!("\r Replaying {}/{repeat}", i + 1);
+            if let Some(elapsed) = self.target.time_to_fail(sequence).await? {
+                time_to_fail_secs.push(elapsed.as_secs_f64());
+            }
+        }
+        eprintln!();
+        self.target.reset().await?;
+        self.target.wait_until_healthy().await;
+
+        let reproducibility = Reproducibility::new(repeat, time_to_fail_secs);
+        tracing::info!(
+            "Reproduced the crash {}/{} times, the crash is {:?}.",
+            reproducibility.reproduced,
+            reproducibility.attempts,
+            reproducibility.verdict
+        );
+        Ok(reproducibility)
+    }
+
+    /// Shrinks the history to a minimal sequence which still crashes the target. The target is
+    /// reset before every trial.
+    async fn find_the_crash_sequence(
+        &mut self,
+        crash: Crash,
+        output: &Output,
+    ) -> ProFuzzResult<TriageResult> {
+        let crash_dir = output.get_crash_dir(crash.id);
+        let history: Vec<Vec<u8>> = crash.history.into_iter().map(|b| b.buffer).collect();
+        let mut result = TriageResult::new(crash.id);
+        tracing::info!("Replaying all {} buffers.", history.len());
+        if !self.target.replay(&history).await? {
+            tracing::warn!("Replaying the whole history did not crash the target.");
+            return Ok(result);
+        }
+        let target = &mut self.target;
+        let sequence = ddmin(history, async |sequence: &[Vec<u8>]| {
+            target.replay(sequence).await
+        })
+        .await?;
+        self.target.reset().await?;
+        self.target.wait_until_healthy().await;
+
+        tracing::info!("Identified a sequence of {} buffers.", sequence.len());
+        result.set_reproducer(&crash_dir, &sequence, Reproduced::default())?;
+        Ok(result)
     }
 
     /// Sends the buffer to the target and verifies if the target crashed.
-    /// In case of an crash, the buffer is stored in the output dir.
+    /// In case of an crash, the most similar buffer of `similar_corpuses` is returned.
     async fn send_and_detect_crash<M>(
         &mut self,
         buffer: &[u8],
-        output: &Output,
         similar_corpuses: &[Vec<u8>],
-    ) -> ProFuzzResult<bool>
+    ) -> ProFuzzResult<Option<Reproduced>>
     where
         M: Mutable + Corpus + Clone,
     {
         // make sure the target is healthy
-        loop {
-            let is_ok = self.healthcheck.is_ok().await;
-            if is_ok.is_ok_and(|x| x) {
-                break;
-            }
-            sleep(Duration::from_millis(1000)).await;
+        self.target.wait_until_healthy().await;
+        self.target.send(buffer).await;
+
+        if self.target.is_healthy().await {
+            return Ok(None);
         }
-        let _ = self.transport.connect().await;
-        if let Err(err) = self.transport.write(buffer).await {
-            tracing::error!("COULD NOT write: {err}");
+        tracing::info!("TARGET crashed");
+
+        let Some(base) = M::from_bytes(buffer.to_vec()) else {
+            return Err(ProFuzzError::Custom {
+                err_msg: "Could not create a structured representation of the crash".to_string(),
+            });
+        };
+
+        let most_equal = closest_any_buffer(buffer, similar_corpuses);
+        if let Some(most_equal) = most_equal.and_then(|b| M::from_bytes(b.to_owned())) {
+            let marked = mark_differences(&base.show(), &most_equal.show());
+            eprintln!("{marked}");
+        } else {
+            eprintln!("{}", base.show());
         }
-        let mut tmp = [0; 2000];
-        let _ = self.transport.read(&mut tmp).await;
-        let _ = self.transport.close().await;
 
-        let mut crashed = false;
-        loop {
-            let is_ok = self.healthcheck.is_ok().await;
-
-            if !crashed && (is_ok.is_err() || is_ok.as_ref().is_ok_and(|x| !x)) {
-                crashed = true;
-                tracing::info!("TARGET crashed");
-
-                let Some(base) = M::from_bytes(buffer.to_vec()) else {
-                    return Err(ProFuzzError::Custom {
-                        err_msg: "Could not create a structured representation of the crash"
-                            .to_string(),
-                    });
-                };
+        self.target.reset().await?;
+        self.target.wait_until_healthy().await;
+        Ok(Some(Reproduced {
+            closest_non_crashing: most_equal.cloned(),
+        }))
+    }
+}
 
-                let mut min_hamming = usize::MAX;
-                let mut most_equal = &similar_corpuses[0];
-
-                for non_crash in similar_corpuses {
-                    if let Some(hamming) = hamming_distance(non_crash, buffer) {
-                        if hamming == 0 {
-                            continue;
-                        }
-                        if hamming < min_hamming {
-                            most_equal = non_crash;
-                            min_hamming = hamming;
-                        }
-                    }
-                }
+/// Approach used to identify the cause of a crash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriageMode {
+    /// Sends every buffer of the history on its own, assuming a single buffer crashes the target
+    Linear,
+    /// Shrinks the history with delta debugging to a minimal sequence crashing the target, e.g.
+    /// in case one buffer sets up a state which is triggered by another one
+    Ddmin,
+}
 
-                if let Some(most_equal) = M::from_bytes(most_equal.to_owned()) {
-                    let marked = mark_differences(&base.show(), &most_equal.show());
-                    println!("{marked}");
-                } else {
-                    println!("{}", base.show());
-                }
+/// Result of replaying the reproducer of a single crash with `regress_from_output_dir`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RegressionResult {
+    /// Id of the replayed crash
+    pub crash_id: usize,
+    /// Title of the transport of the crashed target
+    pub target: String,
+    /// Number of buffers of the reproducer
+    pub buffers: usize,
+    /// Result of the replay
+    pub status: RegressionStatus,
+}
 
-                {
-                    let mut hash = Sha1::new();
-                    hash.update(buffer);
-                    let hash = hash.finalize();
-                    let file = output.get_crash_folder().join(hex::encode(hash));
-                    if std::fs::write(&file, buffer).is_err() {
-                        tracing::error!("Could not store crash in {}", file.display());
-                    } else {
-                        tracing::info!("Crash stored in {}", file.display());
-                    }
-                }
+/// Result of replaying a reproducer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RegressionStatus {
+    /// The target is still healthy after the replay, so the crash is fixed
+    Passed,
+    /// The reproducer still crashes the target
+    Failed,
+    /// The crash has no reproducer as it was not triaged yet
+    Skipped,
+}
 
-                self.resethandler.reset().await?;
-            }
-            if is_ok.is_ok_and(|x| x) {
-                break;
-            }
-            sleep(Duration::from_millis(1000)).await;
+/// A buffer crashed the target during the triage.
+#[derive(Default)]
+struct Reproduced {
+    closest_non_crashing: Option<Vec<u8>>,
+}
+
+/// Selects which crashes are triaged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriageSelection {
+    /// Asks on stdin for every crash if it should be triaged
+    Interactive,
+    /// Triages all crashes without asking
+    All,
+    /// Only triages the crash with the given id
+    Crash(usize),
+}
+
+/// Result of the triage of a single crash, stored as `crashes/<id>/triage.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriageResult {
+    /// Id of the triaged crash
+    pub crash_id: usize,
+    /// True if the crash was reproduced
+    pub reproduced: bool,
+    /// The (last) buffer which crashed the target, hex encoded
+    pub buffer: Option<String>,
+    /// Path of the stored reproducer
+    pub path: Option<PathBuf>,
+    /// The most similar buffer which did not crash the target, hex encoded
+    pub closest_non_crashing: Option<String>,
+    /// All buffers of the reproducer in case a sequence is needed to crash the target, hex
+    /// encoded
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sequence: Vec<String>,
+    /// The crashing buffer with every field reverted to the closest non-crashing buffer which
+    /// is not necessary for the crash, hex encoded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub isolated: Option<String>,
+    /// Fields of the crashing buffer which are necessary for the crash
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub isolated_fields: Vec<IsolatedField>,
+    /// Fields whose revert made the buffer unparsable, so it is unknown if they are necessary
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub undetermined_fields: Vec<IsolatedField>,
+    /// Lines of `Corpus::show` of the isolated buffer which differ from the non-crashing buffer
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub isolated_lines: Vec<String>,
+    /// How reliably the reproducer crashes the target, only set with `set_repeat`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reproducibility: Option<Reproducibility>,
+}
+
+/// Result of replaying a reproducer multiple times, also stored in the `crash.json` of the crash.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reproducibility {
+    /// Number of replays
+    pub attempts: usize,
+    /// Number of replays which crashed the target
+    pub reproduced: usize,
+    /// Time from sending the first buffer until the crash was detected, for every replay which
+    /// crashed the target
+    pub time_to_fail_secs: Vec<f64>,
+    /// Classification of the crash based on the number of reproductions
+    pub verdict: Verdict,
+}
+
+impl Reproducibility {
+    fn new(attempts: usize, time_to_fail_secs: Vec<f64>) -> Self {
+        let reproduced = time_to_fail_secs.len();
+        let verdict = if reproduced == 0 {
+            Verdict::NotReproducible
+        } else if reproduced < attempts {
+            Verdict::Flaky
+        } else {
+            Verdict::Reliable
+        };
+        Self {
+            attempts,
+            reproduced,
+            time_to_fail_secs,
+            verdict,
         }
-        Ok(crashed)
+    }
+}
+
+/// Classification of a crash based on how often it was reproduced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    /// Every replay crashed the target
+    Reliable,
+    /// Only some replays crashed the target, e.g. due to a race or a flaky health check
+    Flaky,
+    /// No replay crashed the target
+    NotReproducible,
+}
+
+/// A field of the `Corpus` which differs between the crashing and the closest non-crashing
+/// buffer, see `Corpus::fields`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IsolatedField {
+    /// Name of the field
+    pub name: String,
+    /// Value of the field in the crashing buffer
+    pub crashing: String,
+    /// Value of the field in the non-crashing buffer, `None` if it does not have the field
+    pub non_crashing: Option<String>,
+}
+
+impl IsolatedField {
+    fn from_names<M: Corpus>(names: &[String], crash: &M, base: &M) -> Vec<Self> {
+        let value = |fields: &[(String, String)], name: &String| {
+            fields
+                .iter()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value.clone())
+        };
+        let (crash, base) = (crash.fields(), base.fields());
+        names
+            .iter()
+            .map(|name| Self {
+                name: name.clone(),
+                crashing: value(&crash, name).unwrap_or_default(),
+                non_crashing: value(&base, name),
+            })
+            .collect()
+    }
+}
+
+impl TriageResult {
+    fn new(crash_id: usize) -> Self {
+        Self {
+            crash_id,
+            reproduced: false,
+            buffer: None,
+            path: None,
+            closest_non_crashing: None,
+            sequence: vec![],
+            isolated: None,
+            isolated_fields: vec![],
+            undetermined_fields: vec![],
+            isolated_lines: vec![],
+            reproducibility: None,
+        }
+    }
+
+    fn set_reproducer(
+        &mut self,
+        crash_dir: &Path,
+        sequence: &[Vec<u8>],
+        reproduced: Reproduced,
+    ) -> ProFuzzResult<()> {
+        let paths = save_reproducer(crash_dir, sequence)?;
+        tracing::info!("Crash stored in {}", crash_dir.display());
+        self.reproduced = true;
+        self.buffer = sequence.last().map(hex::encode);
+        self.path = paths.last().cloned();
+        self.closest_non_crashing = reproduced.closest_non_crashing.map(hex::encode);
+        if sequence.len() > 1 {
+            self.sequence = sequence.iter().map(hex::encode).collect();
+        }
+        Ok(())
+    }
+
+    /// Loads the result of a triaged crash.
+    pub(crate) fn load(output: &Output, crash_id: usize) -> ProFuzzResult<Option<Self>> {
+        let path = output.get_crash_dir(crash_id).join("triage.json");
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(path)?;
+        Ok(Some(serde_json::from_str(&content)?))
+    }
+
+    fn save(&self, output: &Output) -> ProFuzzResult<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        Output::write_atomic(
+            &output.get_crash_dir(self.crash_id).join("triage.json"),
+            content.as_bytes(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_the_reproducibility() {
+        let verdict = |attempts, reproduced| Reproducibility::new(attempts, vec![0.5; reproduced]);
+        assert_eq!(verdict(5, 0).verdict, Verdict::NotReproducible);
+        assert_eq!(verdict(5, 1).verdict, Verdict::Flaky);
+        assert_eq!(verdict(5, 4).verdict, Verdict::Flaky);
+        assert_eq!(verdict(5, 5).verdict, Verdict::Reliable);
+        assert_eq!(verdict(1, 1).verdict, Verdict::Reliable);
+        assert_eq!(verdict(5, 4).reproduced, 4);
     }
 }
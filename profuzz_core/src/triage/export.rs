@@ -0,0 +1,351 @@
+use crate::error::ProFuzzResult;
+use crate::fuzz::stats::Stats;
+use crate::output::Output;
+use crate::triage::dynamic::{TriageResult, Verdict};
+use crate::triage::report::escape;
+use crate::types::{CRASH_BUFFER_FILE, Crash, Timeout, reproducer_files};
+use chrono::SecondsFormat;
+use serde_json::json;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// Format of the exported findings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// SARIF 2.1.0, stored as `findings.sarif`
+    Sarif,
+    /// `JUnit` XML, stored as `junit.xml`
+    Junit,
+}
+
+impl ExportFormat {
+    /// Default file name of the export in the output directory
+    #[must_use]
+    pub fn file_name(self) -> &'static str {
+        match self {
+            ExportFormat::Sarif => "findings.sarif",
+            ExportFormat::Junit => "junit.xml",
+        }
+    }
+}
+
+/// A crash as exported, with the files which reproduce it.
+struct Finding {
+    crash: Crash,
+    triage: Option<TriageResult>,
+    /// The reproducer or the last sent buffer in case the crash was not triaged yet
+    files: Vec<PathBuf>,
+}
+
+impl Finding {
+    fn message(&self) -> String {
+        let mut message = format!("Target {} crashed", self.crash.target);
+        if let Some(failure) = &self.crash.healthcheck_failure {
+            let _ = write!(message, ", the health check failed with: {failure}");
+        }
+        let _ = write!(message, ". {}", self.status());
+        message
+    }
+
+    fn status(&self) -> String {
+        let triaged = match &self.triage {
+            None => "Not triaged",
+            Some(triage) if triage.reproduced => "Reproduced",
+            Some(_) => "Not reproduced",
+        };
+        match &self.crash.reproducibility {
+            Some(r) => format!(
+                "{triaged}, {:?} ({}/{} replays).",
+                r.verdict, r.reproduced, r.attempts
+            ),
+            None => format!("{triaged}."),
+        }
+    }
+}
+
+fn timeout_message(timeout: &Timeout) -> String {
+    let operation = if timeout.writing {
+        "writing the buffer"
+    } else {
+        "reading the response"
+    };
+    format!("Target {} timed out while {operation}.", timeout.target)
+}
+
+/// Exports the crashes and timeouts of the output directory, e.g. for CI dashboards. Every
+/// crash and every stored timeout becomes a result or a failing test case referencing its
+/// reproducer by path.
+/// # Errors
+pub fn export_from_output_dir(output: &Output, format: ExportFormat) -> ProFuzzResult<String> {
+    let mut findings = vec![];
+    for id in output.get_crash_ids()? {
+        let crash = Crash::load_meta(output, id)?;
+        let dir = output.get_crash_dir(id);
+        let mut files = reproducer_files(&dir);
+        if files.is_empty() {
+            files.push(dir.join(CRASH_BUFFER_FILE));
+        }
+        findings.push(Finding {
+            triage: TriageResult::load(output, id)?,
+            crash,
+            files,
+        });
+    }
+    let mut timeouts = vec![];
+    for id in output.get_timeout_ids()? {
+        timeouts.push(Timeout::load(output, id)?);
+    }
+    let stats = Stats::load(output)?.unwrap_or_default();
+    match format {
+        ExportFormat::Sarif => sarif(output, &findings, &timeouts, &stats),
+        ExportFormat::Junit => Ok(junit(output, &findings, &timeouts, &stats)),
+    }
+}
+
+fn sarif(
+    output: &Output,
+    findings: &[Finding],
+    timeouts: &[Timeout],
+    stats: &Stats,
+) -> ProFuzzResult<String> {
+    let out_dir = output.get_path();
+    let base = std::path::absolute(out_dir)?;
+    let uri = |path: &Path| {
+        path.strip_prefix(out_dir)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/")
+    };
+    let mut results: Vec<serde_json::Value> = findings
+        .iter()
+        .map(|finding| {
+            let level = match finding.crash.reproducibility.as_ref().map(|r| r.verdict) {
+                Some(Verdict::Flaky) => "warning",
+                Some(Verdict::NotReproducible) => "note",
+                _ => "error",
+            };
+            let locations: Vec<serde_json::Value> = finding
+                .files
+                .iter()
+                .map(|file| {
+                    json!({
+                        "physicalLocation": {
+                            "artifactLocation": { "uri": uri(file), "uriBaseId": "OUTDIR" }
+                        }
+                    })
+                })
+                .collect();
+            json!({
+                "ruleId": "crash",
+                "level": level,
+                "message": { "text": finding.message() },
+                "locations": locations,
+                "properties": {
+                    "crashId": finding.crash.id,
+                    "target": finding.crash.target,
+                    "worker": finding.crash.worker,
+                    "detectedAt": finding.crash.detected_at,
+                    "cluster": finding.crash.cluster,
+                    "reproduced": finding.triage.as_ref().map(|t| t.reproduced),
+                    "verdict": finding.crash.reproducibility.as_ref().map(|r| r.verdict),
+                }
+            })
+        })
+        .collect();
+    results.extend(timeouts.iter().map(|timeout| {
+        let file = output.get_timeout_dir(timeout.id).join(CRASH_BUFFER_FILE);
+        json!({
+            "ruleId": "timeout",
+            "level": "warning",
+            "message": { "text": timeout_message(timeout) },
+            "locations": [{
+                "physicalLocation": {
+                    "artifactLocation": { "uri": uri(&file), "uriBaseId": "OUTDIR" }
+                }
+            }],
+            "properties": {
+                "timeoutId": timeout.id,
+                "target": timeout.target,
+                "worker": timeout.worker,
+                "detectedAt": timeout.detected_at,
+            }
+        })
+    }));
+    let sarif = json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "profuzz",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": [
+                        {
+                            "id": "crash",
+                            "shortDescription": { "text": "The health check of the target failed" }
+                        },
+                        {
+                            "id": "timeout",
+                            "shortDescription": { "text": "The target did not respond in time" }
+                        }
+                    ]
+                }
+            },
+            "originalUriBaseIds": {
+                "OUTDIR": { "uri": format!("file://{}/", base.display()) }
+            },
+            "results": results,
+            "properties": { "totalTimeouts": stats.total_timeouts }
+        }]
+    });
+    Ok(serde_json::to_string_pretty(&sarif)?)
+}
+
+fn junit(output: &Output, findings: &[Finding], timeouts: &[Timeout], stats: &Stats) -> String {
+    let name = if stats.title.is_empty() {
+        "profuzz"
+    } else {
+        stats.title.as_str()
+    };
+    let tests = findings.len() + timeouts.len();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<testsuites name=\"profuzz\" tests=\"{tests}\" failures=\"{tests}\">"
+    );
+    let _ = writeln!(
+        xml,
+        "  <testsuite name=\"{}\" tests=\"{tests}\" failures=\"{tests}\">",
+        escape(name)
+    );
+    let _ = writeln!(
+        xml,
+        "    <properties>\n      <property name=\"totalTimeouts\" value=\"{}\"/>\n    </properties>",
+        stats.total_timeouts
+    );
+    for finding in findings {
+        let crash = &finding.crash;
+        let mut details = finding.message();
+        if let Some(detected_at) = crash.detected_at {
+            let _ = write!(
+                details,
+                "\nDetected at {}",
+                detected_at.to_rfc3339_opts(SecondsFormat::Micros, true)
+            );
+        }
+        let attachments: Vec<String> = finding
+            .files
+            .iter()
+            .map(|file| {
+                let path = std::path::absolute(file).unwrap_or_else(|_| file.clone());
+                format!("[[ATTACHMENT|{}]]", path.display())
+            })
+            .collect();
+        let _ = writeln!(
+            xml,
+            "    <testcase classname=\"profuzz.crashes\" name=\"crash {}\">",
+            crash.id
+        );
+        let _ = writeln!(
+            xml,
+            "      <failure type=\"crash\" message=\"{}\">{}</failure>",
+            escape(&finding.message()),
+            escape(&details)
+        );
+        let _ = writeln!(
+            xml,
+            "      <system-out>{}</system-out>",
+            escape(&attachments.join("\n"))
+        );
+        xml.push_str("    </testcase>\n");
+    }
+    for timeout in timeouts {
+        let message = timeout_message(timeout);
+        let details = format!(
+            "{message}\nDetected at {}",
+            timeout
+                .detected_at
+                .to_rfc3339_opts(SecondsFormat::Micros, true)
+        );
+        let file = output.get_timeout_dir(timeout.id).join(CRASH_BUFFER_FILE);
+        let path = std::path::absolute(&file).unwrap_or(file);
+        let _ = writeln!(
+            xml,
+            "    <testcase classname=\"profuzz.timeouts\" name=\"timeout {}\">",
+            timeout.id
+        );
+        let _ = writeln!(
+            xml,
+            "      <failure type=\"timeout\" message=\"{}\">{}</failure>",
+            escape(&message),
+            escape(&details)
+        );
+        let _ = writeln!(
+            xml,
+            "      <system-out>{}</system-out>",
+            escape(&format!("[[ATTACHMENT|{}]]", path.display()))
+        );
+        xml.push_str("    </testcase>\n");
+    }
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{TempOutput, legacy_crash};
+    use crate::types::save_reproducer;
+
+    #[test]
+    fn exports_crashes_and_timeouts_with_their_reproducer() {
+        let temp = TempOutput::with_legacy_crashes(
+            "export",
+            &[
+                legacy_crash(&[1], &[&[1]], 0),
+                legacy_crash(&[2], &[&[2]], 0),
+            ],
+        );
+        let output = &temp.output;
+        save_reproducer(&output.get_crash_dir(0), &[vec![1]]).expect("save reproducer");
+        Timeout {
+            id: 0,
+            buffer: vec![3],
+            detected_at: chrono::Utc::now(),
+            writing: false,
+            worker: 0,
+            target: "target".to_string(),
+        }
+        .save(output)
+        .expect("save timeout");
+
+        let sarif = export_from_output_dir(output, ExportFormat::Sarif).expect("export sarif");
+        let sarif: serde_json::Value = serde_json::from_str(&sarif).expect("parse sarif");
+        let uris: Vec<&str> = sarif["runs"][0]["results"]
+            .as_array()
+            .expect("results")
+            .iter()
+            .filter_map(|result| {
+                result["locations"][0]["physicalLocation"]["artifactLocation"]["uri"].as_str()
+            })
+            .collect();
+        assert_eq!(
+            uris,
+            vec![
+                "crashes/0/reproducer.bin",
+                "crashes/1/buffer.bin",
+                "timeouts/0/buffer.bin"
+            ]
+        );
+        assert_eq!(
+            sarif["runs"][0]["results"][2]["ruleId"].as_str(),
+            Some("timeout")
+        );
+
+        let junit = export_from_output_dir(output, ExportFormat::Junit).expect("export junit");
+        assert!(junit.contains("tests=\"3\" failures=\"3\""));
+        assert!(junit.contains("reproducer.bin]]"));
+        assert!(junit.contains("name=\"timeout 0\""));
+        assert!(junit.contains("timeouts/0/buffer.bin]]"));
+    }
+}
@@ -0,0 +1,169 @@
+use crate::error::ProFuzzResult;
+use crate::traits::{Corpus, HealthCheck, ResetHandler, Transport};
+use crate::triage::ddmin::ddmin;
+use crate::triage::replay::Replayer;
+
+/// Outcome of sending a corpus with reverted fields to the target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Revert {
+    /// The reverted corpus still crashes the target
+    Crashes,
+    /// The reverted corpus does not crash the target anymore
+    NoCrash,
+    /// The fields could not be reverted or the reverted corpus can not be parsed anymore, so it
+    /// was not sent
+    Unparsable,
+}
+
+/// Fields which were identified by `isolate_fields`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct Isolation {
+    /// Fields which are necessary for the crash
+    pub(crate) necessary: Vec<String>,
+    /// Fields whose revert made the corpus unparsable, so it is unknown if they are necessary
+    pub(crate) undetermined: Vec<String>,
+}
+
+/// Returns the names of the fields of `crash` whose value differs from `base`, including fields
+/// which `base` does not have.
+pub(crate) fn differing_fields<M: Corpus>(crash: &M, base: &M) -> Vec<String> {
+    let base = base.fields();
+    crash
+        .fields()
+        .into_iter()
+        .filter(|field| !base.contains(field))
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// Reverts the given fields of the crashing corpus to their values in the non-crashing corpus.
+/// Returns `None` if one of the fields can not be reverted.
+pub(crate) fn revert<M: Corpus + Clone>(crash: &M, base: &M, fields: &[&String]) -> Option<M> {
+    let mut reverted = crash.clone();
+    for field in fields {
+        if !reverted.copy_field(field, base) {
+            return None;
+        }
+    }
+    Some(reverted)
+}
+
+/// Reverts the fields of the crashing corpus which differ from the non-crashing corpus on the
+/// live target, first one at a time and then in combinations. Returns the fields which are
+/// necessary for the crash, so reverting all other fields still crashes the target.
+pub(crate) async fn isolate_fields<M, T, H, R>(
+    target: &mut Replayer<T, H, R>,
+    crash: &M,
+    base: &M,
+) -> ProFuzzResult<Isolation>
+where
+    M: Corpus + Clone,
+    T: Transport,
+    H: HealthCheck,
+    R: ResetHandler,
+{
+    let fields = differing_fields(crash, base);
+    tracing::info!("The corpora differ in {} fields.", fields.len());
+
+    // every field whose revert alone stops the crash is necessary
+    let mut isolation = Isolation::default();
+    let mut optional = vec![];
+    for field in fields {
+        match send_reverted(target, revert(crash, base, &[&field])).await? {
+            Revert::Crashes => optional.push(field),
+            Revert::NoCrash => isolation.necessary.push(field),
+            Revert::Unparsable => isolation.undetermined.push(field),
+        }
+    }
+
+    // the optional fields can still be necessary in combination, so only keep the minimal set
+    // of them which is needed to crash the target
+    let all_optional: Vec<&String> = optional.iter().collect();
+    if !optional.is_empty()
+        && send_reverted(target, revert(crash, base, &all_optional)).await? != Revert::Crashes
+    {
+        let mut unparsable = false;
+        let kept = ddmin(optional.clone(), async |kept: &[String]| {
+            let reverted: Vec<&String> = optional.iter().filter(|f| !kept.contains(f)).collect();
+            let outcome = send_reverted(target, revert(crash, base, &reverted)).await?;
+            unparsable |= outcome == Revert::Unparsable;
+            Ok(outcome == Revert::Crashes)
+        })
+        .await?;
+        // an unparsable combination is treated as not crashing, so the kept fields are only
+        // known to be necessary if every combination could be sent
+        if unparsable {
+            isolation.undetermined.extend(kept);
+        } else {
+            isolation.necessary.extend(kept);
+        }
+    }
+    target.reset().await?;
+    target.wait_until_healthy().await;
+    Ok(isolation)
+}
+
+/// Builds the reverted corpus, so fixups like checksums are valid, and tests if it crashes the
+/// target. It is not sent if it can not be parsed anymore.
+async fn send_reverted<M, T, H, R>(
+    target: &mut Replayer<T, H, R>,
+    reverted: Option<M>,
+) -> ProFuzzResult<Revert>
+where
+    M: Corpus,
+    T: Transport,
+    H: HealthCheck,
+    R: ResetHandler,
+{
+    let Some(reverted) = reverted else {
+        return Ok(Revert::Unparsable);
+    };
+    let buffer = reverted.build();
+    if M::from_bytes(buffer.clone()).is_none() {
+        return Ok(Revert::Unparsable);
+    }
+    Ok(if target.replay(&[buffer]).await? {
+        Revert::Crashes
+    } else {
+        Revert::NoCrash
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{FakeTarget, Message};
+
+    #[test]
+    fn reverts_fields_of_different_length() {
+        let crash = Message(vec![1, 0x90, 5, 6]);
+        let base = Message(vec![0, 0x10]);
+        assert_eq!(
+            differing_fields(&crash, &base),
+            vec!["kind", "value", "payload"]
+        );
+        let payload = "payload".to_string();
+        assert_eq!(
+            revert(&crash, &base, &[&payload]),
+            Some(Message(vec![1, 0x90]))
+        );
+        assert_eq!(revert(&crash, &base, &[&"crc".to_string()]), None);
+    }
+
+    #[test]
+    fn reports_unparsable_reverts_as_undetermined() {
+        // only the value crashes the target, reverting the kind alone is not parsable
+        let mut target = FakeTarget::replayer(|buffer| buffer[1] >= 0x80);
+        let crash = Message(vec![1, 0x90, 5, 6]);
+        let base = Message(vec![0, 0x10]);
+        let isolation = futures::executor::block_on(isolate_fields(&mut target, &crash, &base))
+            .expect("isolate");
+        assert_eq!(
+            isolation,
+            Isolation {
+                necessary: vec!["value".to_string()],
+                undetermined: vec!["kind".to_string()],
+            }
+        );
+    }
+}
@@ -0,0 +1,334 @@
+use crate::error::{ProFuzzError, ProFuzzResult};
+use crate::mutator::Mutator;
+use crate::output::Output;
+use crate::traits::{Corpus, HealthCheck, Mutable, ResetHandler, Transport};
+use crate::triage::isolate::differing_fields;
+use crate::triage::replay::Replayer;
+use crate::types::{Crash, SavedQueue, load_reproducer, save_reproducer};
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+/// Number of differently seeded attempts to zero the fields of the corpus in every round
+const ZERO_ATTEMPTS: u64 = 16;
+
+/// Shrinks the buffer which crashes the target while it still crashes it. The candidates are
+/// parsed and built with the `Corpus` implementation, so fixups like checksums stay valid.
+/// The following simplifications are tried until none of them makes progress:
+/// - dropping chunks of bytes
+/// - reverting the fields which differ from the closest queue entry to the seed value, see
+///   `Corpus::fields`
+/// - zeroing fields using the `Mutable` implementation
+pub struct Minimizer<T, H, R>
+where
+    T: Transport,
+    H: HealthCheck,
+    R: ResetHandler,
+{
+    target: Replayer<T, H, R>,
+    max_trials: usize,
+    trials: usize,
+}
+
+/// Result of the minimization of a crash, stored as `crashes/<id>/minimize.json`.
+#[derive(Debug, Clone, Serialize)]
+pub struct MinimizeResult {
+    /// Id of the minimized crash
+    pub crash_id: usize,
+    /// False if the reproducer did not crash the target anymore
+    pub reproduced: bool,
+    /// Length of the buffer before the minimization
+    pub original_len: usize,
+    /// Length of the minimized buffer
+    pub minimized_len: usize,
+    /// Number of times the target was tested
+    pub trials: usize,
+    /// Path of the minimized reproducer
+    pub path: Option<PathBuf>,
+}
+
+impl<T, H, R> Minimizer<T, H, R>
+where
+    T: Transport,
+    H: HealthCheck,
+    R: ResetHandler,
+{
+    /// Creates a new minimizer
+    pub fn new(transport: T, healthcheck: H, resethandler: R) -> Self {
+        Self {
+            target: Replayer::new(transport, healthcheck, resethandler),
+            max_trials: 200,
+            trials: 0,
+        }
+    }
+
+    /// Sets how often the target is tested at most, as every trial resets the target
+    pub fn set_max_trials(&mut self, max_trials: usize) {
+        self.max_trials = max_trials;
+    }
+
+    /// Minimizes the reproducer of the crash, or the last sent buffer in case the crash was not
+    /// triaged yet, and stores the result as new reproducer. In case the reproducer is a
+    /// sequence only its last buffer is minimized.
+    /// # Errors
+    pub async fn minimize_from_output_dir<M: Mutable + Corpus + Clone>(
+        &mut self,
+        output: &Output,
+        id: usize,
+    ) -> ProFuzzResult<MinimizeResult> {
+        let crash_dir = output.get_crash_dir(id);
+        if !crash_dir.is_dir() {
+            return Err(ProFuzzError::Custom {
+                err_msg: format!("Crash {id} does not exist."),
+            });
+        }
+        let mut prefix = match load_reproducer(&crash_dir)? {
+            Some(sequence) => sequence,
+            None => vec![Crash::load_meta(output, id)?.buffer],
+        };
+        let Some(original) = prefix.pop() else {
+            return Err(ProFuzzError::Custom {
+                err_msg: format!("Crash {id} has no buffer to minimize."),
+            });
+        };
+        let seeds: Vec<Vec<u8>> = SavedQueue::load(output)?
+            .map(|queue| queue.entries.into_iter().map(|e| e.corpus).collect())
+            .unwrap_or_default();
+
+        self.trials = 0;
+        let mut result = MinimizeResult {
+            crash_id: id,
+            reproduced: false,
+            original_len: original.len(),
+            minimized_len: original.len(),
+            trials: 0,
+            path: None,
+        };
+        if !self.crashes(&prefix, &original).await? {
+            tracing::warn!("The reproducer of crash {id} did not crash the target.");
+            result.trials = self.trials;
+            return Ok(result);
+        }
+        result.reproduced = true;
+
+        let mut current = original;
+        // reverting to the seed and zeroing can undo each other, so stop at a known buffer
+        let mut seen = HashSet::from([current.clone()]);
+        loop {
+            current = self.drop_bytes::<M>(&prefix, current).await?;
+            current = self.revert_to_seed::<M>(&prefix, current, &seeds).await?;
+            current = self.zero_fields::<M>(&prefix, current).await?;
+            if !seen.insert(current.clone()) || self.trials >= self.max_trials {
+                break;
+            }
+        }
+        self.target.reset().await?;
+        self.target.wait_until_healthy().await;
+
+        if let Some(corpus) = M::from_bytes(current.clone()) {
+            tracing::info!("Minimized reproducer:\n{}", corpus.show());
+        }
+        result.minimized_len = current.len();
+        result.trials = self.trials;
+        prefix.push(current);
+        result.path = save_reproducer(&crash_dir, &prefix)?.pop();
+        let content = serde_json::to_string_pretty(&result)?;
+        Output::write_atomic(&crash_dir.join("minimize.json"), content.as_bytes())?;
+        Ok(result)
+    }
+
+    /// Resets the target and sends the prefix followed by the candidate.
+    async fn crashes(&mut self, prefix: &[Vec<u8>], candidate: &[u8]) -> ProFuzzResult<bool> {
+        self.trials += 1;
+        let mut sequence = prefix.to_vec();
+        sequence.push(candidate.to_vec());
+        self.target.replay(&sequence).await
+    }
+
+    fn exhausted(&self) -> bool {
+        self.trials >= self.max_trials
+    }
+
+    /// Drops chunks of bytes, starting with half of the buffer down to single bytes.
+    async fn drop_bytes<M: Corpus>(
+        &mut self,
+        prefix: &[Vec<u8>],
+        mut current: Vec<u8>,
+    ) -> ProFuzzResult<Vec<u8>> {
+        let mut chunk = current.len() / 2;
+        while chunk > 0 && !self.exhausted() {
+            let mut start = 0;
+            while start < current.len() && !self.exhausted() {
+                let end = (start + chunk).min(current.len());
+                let mut candidate = current[..start].to_vec();
+                candidate.extend_from_slice(&current[end..]);
+                if let Some(candidate) = normalize::<M>(candidate)
+                    && is_smaller(&candidate, &current)
+                    && self.crashes(prefix, &candidate).await?
+                {
+                    tracing::info!("Dropped bytes, {} bytes left.", candidate.len());
+                    current = candidate;
+                } else {
+                    start += chunk;
+                }
+            }
+            chunk /= 2;
+        }
+        Ok(current)
+    }
+
+    /// Reverts every field differing from the queue entry with the fewest differing fields to
+    /// its value in the entry, unless it makes the buffer longer. The fields are compared by
+    /// name, so the entry can have a different length.
+    async fn revert_to_seed<M: Corpus + Clone>(
+        &mut self,
+        prefix: &[Vec<u8>],
+        mut current: Vec<u8>,
+        seeds: &[Vec<u8>],
+    ) -> ProFuzzResult<Vec<u8>> {
+        let Some(corpus) = M::from_bytes(current.clone()) else {
+            return Ok(current);
+        };
+        let Some((mut distance, seed)) = seeds
+            .iter()
+            .filter_map(|seed| M::from_bytes(seed.clone()))
+            .map(|seed| (differing_fields(&corpus, &seed).len(), seed))
+            .filter(|(distance, _)| *distance > 0)
+            .min_by_key(|(distance, _)| *distance)
+        else {
+            return Ok(current);
+        };
+        for field in differing_fields(&corpus, &seed) {
+            if self.exhausted() {
+                break;
+            }
+            let Some(mut candidate) = M::from_bytes(current.clone()) else {
+                break;
+            };
+            if !candidate.copy_field(&field, &seed) {
+                continue;
+            }
+            let candidate = candidate.build();
+            let Some(reverted) = M::from_bytes(candidate.clone()) else {
+                continue;
+            };
+            let candidate_distance = differing_fields(&reverted, &seed).len();
+            if candidate_distance < distance
+                && candidate.len() <= current.len()
+                && self.crashes(prefix, &candidate).await?
+            {
+                tracing::info!("Reverted the field {field} to the seed.");
+                current = candidate;
+                distance = candidate_distance;
+            }
+        }
+        Ok(current)
+    }
+
+    /// Zeroes randomly selected fields with the `Mutable` implementation.
+    async fn zero_fields<M: Corpus + Mutable>(
+        &mut self,
+        prefix: &[Vec<u8>],
+        mut current: Vec<u8>,
+    ) -> ProFuzzResult<Vec<u8>> {
+        for seed in 0..ZERO_ATTEMPTS {
+            if self.exhausted() {
+                break;
+            }
+            let Some(mut corpus) = M::from_bytes(current.clone()) else {
+                break;
+            };
+            corpus.mutate(&mut Mutator::zeroing(ChaChaRng::seed_from_u64(seed)));
+            let Some(candidate) = normalize::<M>(corpus.build()) else {
+                continue;
+            };
+            if is_smaller(&candidate, &current) && self.crashes(prefix, &candidate).await? {
+                tracing::info!(
+                    "Zeroed fields, {} non-zero bytes left.",
+                    non_zero(&candidate)
+                );
+                current = candidate;
+            }
+        }
+        Ok(current)
+    }
+}
+
+/// Parses and builds the buffer, so fixups like checksums or length fields are valid.
+fn normalize<M: Corpus>(buffer: Vec<u8>) -> Option<Vec<u8>> {
+    Some(M::from_bytes(buffer)?.build())
+}
+
+fn non_zero(buffer: &[u8]) -> usize {
+    buffer.iter().filter(|b| **b != 0).count()
+}
+
+/// A buffer is smaller if it is shorter or has less non-zero bytes.
+fn is_smaller(candidate: &[u8], current: &[u8]) -> bool {
+    (candidate.len(), non_zero(candidate)) < (current.len(), non_zero(current))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{FakeTarget, Message, TempOutput, legacy_crash};
+    use crate::types::SavedQueueEntry;
+
+    #[test]
+    fn minimizes_with_a_seed_of_different_length() {
+        let temp = TempOutput::with_legacy_crashes(
+            "minimize",
+            &[legacy_crash(
+                &[3, 0x90, 1, 2, 3, 4, 5, 6, 7, 8],
+                &[&[3, 0x90, 1, 2, 3, 4, 5, 6, 7, 8]],
+                0,
+            )],
+        );
+        let output = &temp.output;
+        SavedQueue {
+            entries: vec![SavedQueueEntry {
+                corpus: vec![2, 0x10, 9],
+                response_hash: None,
+                found_at_execution: 0,
+            }],
+            position: 0,
+            unique_responses: vec![],
+        }
+        .save(output)
+        .expect("save queue");
+
+        // the target crashes on every value of 0x80 or more
+        let crashes = |buffer: &[u8]| buffer.get(1).is_some_and(|value| *value >= 0x80);
+        let target = FakeTarget::new(crashes);
+        let mut minimizer = Minimizer::new(target.clone(), target.clone(), target);
+        let result =
+            futures::executor::block_on(minimizer.minimize_from_output_dir::<Message>(output, 0))
+                .expect("minimize");
+        assert!(result.reproduced);
+        assert!(result.minimized_len < result.original_len);
+
+        let reproducer = load_reproducer(&output.get_crash_dir(0))
+            .expect("load reproducer")
+            .expect("reproducer");
+        // the payload is dropped and the kind is reverted to the seed, while zeroing the kind
+        // alone is not parsable
+        assert_eq!(reproducer, vec![vec![2, 0x90]]);
+        assert!(crashes(&reproducer[0]));
+    }
+
+    #[test]
+    fn reverts_fields_to_a_seed_of_different_length() {
+        let target = FakeTarget::new(|buffer| buffer[1] >= 0x80);
+        let mut minimizer = Minimizer::new(target.clone(), target.clone(), target);
+        let current = vec![3, 0x90, 1, 2, 3, 4];
+        let reverted = futures::executor::block_on(minimizer.revert_to_seed::<Message>(
+            &[],
+            current,
+            &[vec![2, 0x10, 7]],
+        ))
+        .expect("revert");
+        assert_eq!(reverted, vec![2, 0x90, 7]);
+    }
+}
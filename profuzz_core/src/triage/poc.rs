@@ -0,0 +1,451 @@
+use crate::error::{ProFuzzError, ProFuzzResult};
+use crate::output::Output;
+use crate::traits::TransportMetadata;
+use crate::types::{Crash, TargetInfo, load_reproducer};
+use std::fmt::Write;
+use std::path::PathBuf;
+
+/// Language of the generated proof of concept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum PocLanguage {
+    /// A Rust program stored as `crashes/<id>/poc.rs`, only using the standard library except
+    /// for raw ethernet frames which need the `pnet` crate
+    Rust,
+    /// A Python 3 script stored as `crashes/<id>/poc.py` without dependencies
+    Python,
+}
+
+/// Generates a self-contained proof of concept which connects to the target like the
+/// `Transport` of the campaign, using the metadata stored in `target.json`, and sends the
+/// reproducer of the crash. In case the crash was not triaged yet, the last buffer sent before
+/// the crash is used instead. Returns the path of the generated file.
+/// # Errors
+/// Returns an error if the crash does not exist or no transport metadata was recorded.
+pub fn poc_from_output_dir(
+    output: &Output,
+    id: usize,
+    language: PocLanguage,
+) -> ProFuzzResult<PathBuf> {
+    let crash_dir = output.get_crash_dir(id);
+    if !crash_dir.is_dir() {
+        return Err(ProFuzzError::Custom {
+            err_msg: format!("Crash {id} does not exist."),
+        });
+    }
+    let crash = Crash::load_meta(output, id)?;
+    let buffers = if let Some(sequence) = load_reproducer(&crash_dir)? {
+        sequence
+    } else {
+        tracing::warn!("Crash {id} was not triaged yet, using the last sent buffer.");
+        vec![crash.buffer.clone()]
+    };
+    let targets = TargetInfo::load_all(output)?;
+    let target = targets
+        .iter()
+        .find(|target| target.title == crash.target)
+        .or_else(|| targets.iter().find(|target| target.worker == crash.worker))
+        .filter(|target| target.transport != TransportMetadata::Unknown)
+        .ok_or_else(|| ProFuzzError::Custom {
+            err_msg: format!(
+                "No connection parameters of {} were recorded, the transport has to implement `Transport::metadata`.",
+                crash.target
+            ),
+        })?;
+
+    let header = format!(
+        "Proof of concept for crash {id} of {}, generated by profuzz.",
+        target.title
+    );
+    let (content, file_name) = match language {
+        PocLanguage::Rust => (rust(&header, &target.transport, &buffers)?, "poc.rs"),
+        PocLanguage::Python => (python(&header, &target.transport, &buffers)?, "poc.py"),
+    };
+    let path = crash_dir.join(file_name);
+    Output::write_atomic(&path, content.as_bytes())?;
+    Ok(path)
+}
+
+/// Returns the header as a single line, as it is inserted into a line comment.
+fn single_line(header: &str) -> String {
+    header
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect()
+}
+
+fn unknown_transport() -> ProFuzzError {
+    ProFuzzError::Custom {
+        err_msg: "The transport did not record its connection parameters.".to_string(),
+    }
+}
+
+fn rust_bytes(buffers: &[Vec<u8>]) -> String {
+    let mut code = String::new();
+    for buffer in buffers {
+        let lines: Vec<String> = buffer
+            .chunks(16)
+            .map(|chunk| {
+                let bytes: Vec<String> = chunk.iter().map(|b| format!("0x{b:02x}")).collect();
+                bytes.join(", ")
+            })
+            .collect();
+        if lines.len() > 1 {
+            let _ = writeln!(
+                code,
+                "    &[\n        {},\n    ],",
+                lines.join(",\n        ")
+            );
+        } else {
+            let _ = writeln!(code, "    &[{}],", lines.join(""));
+        }
+    }
+    code
+}
+
+fn python_bytes(buffers: &[Vec<u8>]) -> String {
+    let mut code = String::new();
+    for buffer in buffers {
+        let _ = writeln!(code, "    bytes.fromhex(\"{}\"),", hex::encode(buffer));
+    }
+    code
+}
+
+fn rust(header: &str, transport: &TransportMetadata, buffers: &[Vec<u8>]) -> ProFuzzResult<String> {
+    let (template, replacements) = match transport {
+        TransportMetadata::Tcp {
+            address,
+            read_timeout,
+            write_timeout,
+            send_after_connected,
+        } => (
+            RUST_TCP,
+            vec![
+                ("@TARGET@", format!("{address:?}")),
+                ("@READ_TIMEOUT@", format!("{read_timeout:?}")),
+                ("@WRITE_TIMEOUT@", write_timeout.to_string()),
+                ("@SEND_AFTER_CONNECTED@", rust_bytes(send_after_connected)),
+            ],
+        ),
+        TransportMetadata::Udp {
+            address,
+            read_timeout,
+        } => (
+            RUST_UDP,
+            vec![
+                ("@TARGET@", format!("{address:?}")),
+                ("@READ_TIMEOUT@", format!("{read_timeout:?}")),
+            ],
+        ),
+        TransportMetadata::RawEthernet { interface } => {
+            (RUST_RAW, vec![("@INTERFACE@", format!("{interface:?}"))])
+        }
+        TransportMetadata::Unknown => return Err(unknown_transport()),
+    };
+    let mut code = template.replace("@BUFFERS@", &rust_bytes(buffers));
+    for (key, value) in replacements {
+        code = code.replace(key, &value);
+    }
+    // replaced last, so placeholders in the title of the target are kept
+    Ok(code.replace("@HEADER@", &single_line(header)))
+}
+
+fn python(
+    header: &str,
+    transport: &TransportMetadata,
+    buffers: &[Vec<u8>],
+) -> ProFuzzResult<String> {
+    let seconds = |millis: &Option<u64>| {
+        millis
+            .filter(|millis| *millis > 0)
+            .map_or("None".to_string(), |millis| {
+                format!("{}.{:03}", millis / 1000, millis % 1000)
+            })
+    };
+    let (template, replacements) = match transport {
+        TransportMetadata::Tcp {
+            address,
+            read_timeout,
+            write_timeout,
+            send_after_connected,
+        } => (
+            PYTHON_TCP,
+            vec![
+                ("@TARGET@", format!("{address:?}")),
+                ("@READ_TIMEOUT@", seconds(read_timeout)),
+                ("@WRITE_TIMEOUT@", seconds(&Some(*write_timeout))),
+                ("@SEND_AFTER_CONNECTED@", python_bytes(send_after_connected)),
+            ],
+        ),
+        TransportMetadata::Udp {
+            address,
+            read_timeout,
+        } => (
+            PYTHON_UDP,
+            vec![
+                ("@TARGET@", format!("{address:?}")),
+                ("@READ_TIMEOUT@", seconds(read_timeout)),
+            ],
+        ),
+        TransportMetadata::RawEthernet { interface } => {
+            (PYTHON_RAW, vec![("@INTERFACE@", format!("{interface:?}"))])
+        }
+        TransportMetadata::Unknown => return Err(unknown_transport()),
+    };
+    let mut code = template.replace("@BUFFERS@", &python_bytes(buffers));
+    for (key, value) in replacements {
+        code = code.replace(key, &value);
+    }
+    // the docstring must not be closed or escaped by the title of the target
+    let header = single_line(header)
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"");
+    Ok(code.replace("@HEADER@", &header))
+}
+
+const RUST_TCP: &str = r#"//! @HEADER@
+//! Build and run it with `rustc poc.rs && ./poc`.
+use std::io::{Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::time::Duration;
+
+const TARGET: &str = @TARGET@;
+/// Read timeout in millis, responses are not read if `None`
+const READ_TIMEOUT: Option<u64> = @READ_TIMEOUT@;
+/// Write timeout in millis
+const WRITE_TIMEOUT: u64 = @WRITE_TIMEOUT@;
+/// Messages sent after every connect
+const SEND_AFTER_CONNECTED: &[&[u8]] = &[
+@SEND_AFTER_CONNECTED@];
+/// Buffers crashing the target, each sent on a new connection
+const BUFFERS: &[&[u8]] = &[
+@BUFFERS@];
+
+fn read(stream: &mut TcpStream) {
+    if READ_TIMEOUT.is_some() {
+        let mut response = [0u8; 3000];
+        let _ = stream.read(&mut response);
+    }
+}
+
+fn main() -> std::io::Result<()> {
+    for buffer in BUFFERS {
+        let mut stream = TcpStream::connect(TARGET)?;
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(READ_TIMEOUT.filter(|t| *t > 0).map(Duration::from_millis))?;
+        stream.set_write_timeout(Some(WRITE_TIMEOUT).filter(|t| *t > 0).map(Duration::from_millis))?;
+        for message in SEND_AFTER_CONNECTED {
+            stream.write_all(message)?;
+            read(&mut stream);
+        }
+        stream.write_all(buffer)?;
+        read(&mut stream);
+        let _ = stream.shutdown(Shutdown::Both);
+    }
+    println!("Sent {} buffers to {TARGET}.", BUFFERS.len());
+    Ok(())
+}
+"#;
+
+const RUST_UDP: &str = r#"//! @HEADER@
+//! Build and run it with `rustc poc.rs && ./poc`.
+use std::net::UdpSocket;
+use std::time::Duration;
+
+const TARGET: &str = @TARGET@;
+/// Read timeout in millis, responses are not read if `None`
+const READ_TIMEOUT: Option<u64> = @READ_TIMEOUT@;
+/// Buffers crashing the target, each sent as datagram
+const BUFFERS: &[&[u8]] = &[
+@BUFFERS@];
+
+fn main() -> std::io::Result<()> {
+    let socket = UdpSocket::bind(if TARGET.starts_with('[') { "[::]:0" } else { "0.0.0.0:0" })?;
+    socket.connect(TARGET)?;
+    socket.set_read_timeout(READ_TIMEOUT.filter(|t| *t > 0).map(Duration::from_millis))?;
+    for buffer in BUFFERS {
+        socket.send(buffer)?;
+        if READ_TIMEOUT.is_some() {
+            let mut response = [0u8; 3000];
+            let _ = socket.recv(&mut response);
+        }
+    }
+    println!("Sent {} buffers to {TARGET}.", BUFFERS.len());
+    Ok(())
+}
+"#;
+
+const RUST_RAW: &str = r#"//! @HEADER@
+//! Requires the `pnet` crate and root permissions, e.g. as `src/main.rs` of a new crate
+//! after `cargo add pnet`.
+use pnet::datalink::{self, Channel::Ethernet, Config};
+
+const INTERFACE: &str = @INTERFACE@;
+/// Ethernet frames crashing the target
+const BUFFERS: &[&[u8]] = &[
+@BUFFERS@];
+
+fn main() {
+    let interface = datalink::interfaces()
+        .into_iter()
+        .find(|interface| interface.name == INTERFACE)
+        .expect("interface not found");
+    let Ok(Ethernet(mut tx, _)) = datalink::channel(&interface, Config::default()) else {
+        panic!("could not open a raw socket on {INTERFACE}");
+    };
+    for buffer in BUFFERS {
+        tx.send_to(buffer, None)
+            .expect("could not send the frame")
+            .expect("could not send the frame");
+    }
+    println!("Sent {} frames on {INTERFACE}.", BUFFERS.len());
+}
+"#;
+
+const PYTHON_TCP: &str = r#"#!/usr/bin/env python3
+"""@HEADER@"""
+import socket
+
+TARGET = @TARGET@
+# Read timeout in seconds, responses are not read if None
+READ_TIMEOUT = @READ_TIMEOUT@
+# Write timeout in seconds
+WRITE_TIMEOUT = @WRITE_TIMEOUT@
+# Messages sent after every connect
+SEND_AFTER_CONNECTED = [
+@SEND_AFTER_CONNECTED@]
+# Buffers crashing the target, each sent on a new connection
+BUFFERS = [
+@BUFFERS@]
+
+
+def read(sock):
+    if READ_TIMEOUT is None:
+        return
+    sock.settimeout(READ_TIMEOUT)
+    try:
+        sock.recv(3000)
+    except OSError:
+        pass
+    sock.settimeout(WRITE_TIMEOUT)
+
+
+host, port = TARGET.rsplit(":", 1)
+for buffer in BUFFERS:
+    with socket.create_connection((host.strip("[]"), int(port)), timeout=WRITE_TIMEOUT) as sock:
+        sock.setsockopt(socket.IPPROTO_TCP, socket.TCP_NODELAY, 1)
+        for message in SEND_AFTER_CONNECTED:
+            sock.sendall(message)
+            read(sock)
+        sock.sendall(buffer)
+        read(sock)
+print(f"Sent {len(BUFFERS)} buffers to {TARGET}.")
+"#;
+
+const PYTHON_UDP: &str = r#"#!/usr/bin/env python3
+"""@HEADER@"""
+import socket
+
+TARGET = @TARGET@
+# Read timeout in seconds, responses are not read if None
+READ_TIMEOUT = @READ_TIMEOUT@
+# Buffers crashing the target, each sent as datagram
+BUFFERS = [
+@BUFFERS@]
+
+host, port = TARGET.rsplit(":", 1)
+family, kind, proto, _, address = socket.getaddrinfo(host.strip("[]"), int(port), type=socket.SOCK_DGRAM)[0]
+with socket.socket(family, kind, proto) as sock:
+    sock.settimeout(READ_TIMEOUT)
+    for buffer in BUFFERS:
+        sock.sendto(buffer, address)
+        if READ_TIMEOUT is not None:
+            try:
+                sock.recv(3000)
+            except OSError:
+                pass
+print(f"Sent {len(BUFFERS)} buffers to {TARGET}.")
+"#;
+
+const PYTHON_RAW: &str = r#"#!/usr/bin/env python3
+"""@HEADER@
+
+Requires root permissions and Linux.
+"""
+import socket
+
+INTERFACE = @INTERFACE@
+# Ethernet frames crashing the target
+BUFFERS = [
+@BUFFERS@]
+
+with socket.socket(socket.AF_PACKET, socket.SOCK_RAW) as sock:
+    sock.bind((INTERFACE, 0))
+    for buffer in BUFFERS:
+        sock.send(buffer)
+print(f"Sent {len(BUFFERS)} frames on {INTERFACE}.")
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_rust_bytes() {
+        assert_eq!(
+            rust_bytes(&[vec![1, 0xff], vec![]]),
+            "    &[0x01, 0xff],\n    &[],\n"
+        );
+        let long = rust_bytes(&[vec![0; 17]]);
+        assert!(long.starts_with("    &[\n        0x00, 0x00,"));
+        assert!(long.ends_with(" 0x00,\n        0x00,\n    ],\n"));
+    }
+
+    #[test]
+    fn renders_udp_templates() {
+        let transport = TransportMetadata::Udp {
+            address: "127.0.0.1:5000".to_string(),
+            read_timeout: Some(1500),
+        };
+        let buffers = [vec![0xde, 0xad]];
+
+        let code = rust("Crash 7", &transport, &buffers).expect("render");
+        assert!(!code.contains('@'));
+        assert!(code.starts_with("//! Crash 7\n"));
+        assert!(code.contains("const TARGET: &str = \"127.0.0.1:5000\";"));
+        assert!(code.contains("const READ_TIMEOUT: Option<u64> = Some(1500);"));
+        assert!(code.contains("    &[0xde, 0xad],\n];"));
+
+        let code = python("Crash 7", &transport, &buffers).expect("render");
+        assert!(!code.contains('@'));
+        assert!(code.contains("\"\"\"Crash 7\"\"\""));
+        assert!(code.contains("TARGET = \"127.0.0.1:5000\"\n"));
+        assert!(code.contains("READ_TIMEOUT = 1.500\n"));
+        assert!(code.contains("socket.SOCK_DGRAM"));
+
+        let transport = TransportMetadata::Udp {
+            address: "127.0.0.1:5000".to_string(),
+            read_timeout: None,
+        };
+        let code = rust("", &transport, &buffers).expect("render");
+        assert!(code.contains("READ_TIMEOUT: Option<u64> = None;"));
+        let code = python("", &transport, &buffers).expect("render");
+        assert!(code.contains("READ_TIMEOUT = None\n"));
+    }
+
+    #[test]
+    fn sanitizes_the_header() {
+        let transport = TransportMetadata::RawEthernet {
+            interface: "eth0".to_string(),
+        };
+        let header = "Crash of \"\"\"@INTERFACE@\nimport os\\";
+
+        let code = rust(header, &transport, &[]).expect("render");
+        assert!(code.starts_with("//! Crash of \"\"\"@INTERFACE@ import os\\\n"));
+
+        let code = python(header, &transport, &[]).expect("render");
+        assert!(code.contains("\"\"\"Crash of \\\"\\\"\\\"@INTERFACE@ import os\\\\\n"));
+
+        assert!(rust(header, &TransportMetadata::Unknown, &[]).is_err());
+        assert!(python(header, &TransportMetadata::Unknown, &[]).is_err());
+    }
+}
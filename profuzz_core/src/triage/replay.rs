@@ -0,0 +1,76 @@
+use crate::error::ProFuzzResult;
+use crate::traits::{HealthCheck, ResetHandler, Transport};
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+
+/// Replays buffers to the target and detects if they crash it. This is shared by the different
+/// triage approaches.
+pub(crate) struct Replayer<T, H, R> {
+    transport: T,
+    healthcheck: H,
+    resethandler: R,
+}
+
+impl<T, H, R> Replayer<T, H, R>
+where
+    T: Transport,
+    H: HealthCheck,
+    R: ResetHandler,
+{
+    pub(crate) fn new(transport: T, healthcheck: H, resethandler: R) -> Self {
+        Self {
+            transport,
+            healthcheck,
+            resethandler,
+        }
+    }
+
+    pub(crate) async fn is_healthy(&mut self) -> bool {
+        self.healthcheck.is_ok().await.is_ok_and(|ok| ok)
+    }
+
+    pub(crate) async fn wait_until_healthy(&mut self) {
+        while !self.is_healthy().await {
+            sleep(Duration::from_secs(1)).await;
+        }
+    }
+
+    pub(crate) async fn reset(&mut self) -> ProFuzzResult<()> {
+        self.resethandler.reset().await
+    }
+
+    /// Sends a single buffer to the target and reads the response.
+    pub(crate) async fn send(&mut self, buffer: &[u8]) {
+        let _ = self.transport.connect().await;
+        if let Err(err) = self.transport.write(buffer).await {
+            tracing::error!("COULD NOT write: {err}");
+        }
+        let mut tmp = [0; 2000];
+        let _ = self.transport.read(&mut tmp).await;
+        let _ = self.transport.close().await;
+    }
+
+    /// Resets the target, sends all buffers of the sequence and returns true if the target
+    /// crashed. Resetting first makes sure no state of a previous trial is left on the target.
+    pub(crate) async fn replay(&mut self, sequence: &[Vec<u8>]) -> ProFuzzResult<bool> {
+        Ok(self.time_to_fail(sequence).await?.is_some())
+    }
+
+    /// Like `replay`, but returns the time from sending the first buffer until the health
+    /// check detected the crash, or `None` if the target did not crash.
+    pub(crate) async fn time_to_fail(
+        &mut self,
+        sequence: &[Vec<u8>],
+    ) -> ProFuzzResult<Option<Duration>> {
+        self.reset().await?;
+        self.wait_until_healthy().await;
+        let started = Instant::now();
+        for buffer in sequence {
+            self.send(buffer).await;
+        }
+        if self.is_healthy().await {
+            return Ok(None);
+        }
+        Ok(Some(started.elapsed()))
+    }
+}
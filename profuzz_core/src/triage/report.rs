@@ -0,0 +1,521 @@
+use crate::error::{ProFuzzError, ProFuzzResult};
+use crate::fuzz::stats::Stats;
+use crate::output::Output;
+use crate::traits::Corpus;
+use crate::triage::dynamic::TriageResult;
+use crate::types::{Crash, load_reproducer};
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::fmt::Write;
+use std::path::PathBuf;
+
+/// Format of the rendered crash reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ReportFormat {
+    /// Stored as `crashes/<id>/report.md`
+    Markdown,
+    /// A standalone page stored as `crashes/<id>/report.html`
+    Html,
+}
+
+impl ReportFormat {
+    fn file_name(self) -> &'static str {
+        match self {
+            ReportFormat::Markdown => "report.md",
+            ReportFormat::Html => "report.html",
+        }
+    }
+}
+
+/// Renders a report of every crash, or only of the given one, which can be handed to the
+/// vendor of the target. Returns the paths of the reports.
+/// # Errors
+pub fn report_from_output_dir<M: Corpus>(
+    output: &Output,
+    format: ReportFormat,
+    crash_id: Option<usize>,
+) -> ProFuzzResult<Vec<PathBuf>> {
+    let ids = match crash_id {
+        Some(id) if output.get_crash_dir(id).is_dir() => vec![id],
+        Some(id) => {
+            return Err(ProFuzzError::Custom {
+                err_msg: format!("Crash {id} does not exist."),
+            });
+        }
+        None => output.get_crash_ids()?,
+    };
+    let mut paths = vec![];
+    for id in ids {
+        let sections = sections::<M>(output, id)?;
+        let content = match format {
+            ReportFormat::Markdown => render_markdown(id, &sections),
+            ReportFormat::Html => render_html(id, &sections),
+        };
+        let path = output.get_crash_dir(id).join(format.file_name());
+        Output::write_atomic(&path, content.as_bytes())?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+enum Block {
+    Table(Vec<(String, String)>),
+    Columns(Vec<String>, Vec<Vec<String>>),
+    Code(String),
+    /// Lines of `Corpus::show`, with the line of the non-crashing buffer for changed lines
+    Diff(Vec<(String, Option<String>)>),
+    Text(String),
+}
+
+struct Section {
+    title: String,
+    blocks: Vec<Block>,
+}
+
+fn section(title: impl Into<String>, blocks: Vec<Block>) -> Section {
+    Section {
+        title: title.into(),
+        blocks,
+    }
+}
+
+fn sections<M: Corpus>(output: &Output, id: usize) -> ProFuzzResult<Vec<Section>> {
+    let crash = Crash::load_meta(output, id)?;
+    let triage = TriageResult::load(output, id)?;
+    let buffers =
+        load_reproducer(&output.get_crash_dir(id))?.unwrap_or_else(|| vec![crash.buffer.clone()]);
+
+    let mut sections = vec![
+        summary(&crash),
+        reproduction(&crash, triage.as_ref()),
+        structure::<M>(buffers.last().map_or(&[], Vec::as_slice), triage.as_ref()),
+    ];
+    if let Some(triage) = triage
+        .as_ref()
+        .filter(|t| !t.isolated_fields.is_empty() || !t.undetermined_fields.is_empty())
+    {
+        let rows = triage
+            .isolated_fields
+            .iter()
+            .map(|f| (f, "necessary"))
+            .chain(
+                triage
+                    .undetermined_fields
+                    .iter()
+                    .map(|f| (f, "undetermined")),
+            )
+            .map(|(f, status)| {
+                vec![
+                    f.name.clone(),
+                    f.crashing.clone(),
+                    f.non_crashing.clone().unwrap_or_else(|| "-".to_string()),
+                    status.to_string(),
+                ]
+            })
+            .collect();
+        let mut blocks = vec![Block::Columns(
+            vec![
+                "Field".to_string(),
+                "Crashing".to_string(),
+                "Non-crashing".to_string(),
+                "Status".to_string(),
+            ],
+            rows,
+        )];
+        if !triage.isolated_lines.is_empty() {
+            blocks.push(Block::Code(triage.isolated_lines.join("\n")));
+        }
+        sections.push(section("Fields necessary for the crash", blocks));
+    }
+    for (i, buffer) in buffers.iter().enumerate() {
+        let title = if buffers.len() > 1 {
+            format!(
+                "Buffer {}/{} ({} bytes)",
+                i + 1,
+                buffers.len(),
+                buffer.len()
+            )
+        } else {
+            format!("Buffer ({} bytes)", buffer.len())
+        };
+        sections.push(section(title, vec![Block::Code(hexdump(buffer))]));
+    }
+    sections.push(stats(&crash.stats));
+    Ok(sections)
+}
+
+fn summary(crash: &Crash) -> Section {
+    section(
+        "Summary",
+        vec![Block::Table(vec![
+            ("Target".to_string(), crash.target.clone()),
+            ("Worker".to_string(), crash.worker.to_string()),
+            ("Detected at".to_string(), format_time(crash.detected_at)),
+            (
+                "Triggering buffer sent at".to_string(),
+                format_time(crash.sent_at),
+            ),
+            (
+                "Last successful health check".to_string(),
+                format_time(crash.last_healthcheck_at),
+            ),
+            (
+                "Health check failure".to_string(),
+                crash
+                    .healthcheck_failure
+                    .clone()
+                    .unwrap_or("unknown".to_string()),
+            ),
+            (
+                "Cluster".to_string(),
+                crash.cluster.map_or("-".to_string(), |c| c.to_string()),
+            ),
+        ])],
+    )
+}
+
+fn reproduction(crash: &Crash, triage: Option<&TriageResult>) -> Section {
+    let status = match triage {
+        None => "not triaged",
+        Some(triage) if triage.reproduced => "reproduced",
+        Some(_) => "not reproduced",
+    };
+    let verdict = crash.reproducibility.as_ref().map_or("-".to_string(), |r| {
+        format!("{:?} ({}/{} replays)", r.verdict, r.reproduced, r.attempts)
+    });
+    let time_to_fail = crash.reproducibility.as_ref().map_or("-".to_string(), |r| {
+        let times: Vec<String> = r
+            .time_to_fail_secs
+            .iter()
+            .map(|t| format!("{t:.3}s"))
+            .collect();
+        times.join(", ")
+    });
+    let mut rows = vec![
+        ("Status".to_string(), status.to_string()),
+        ("Verdict".to_string(), verdict),
+        ("Time to fail".to_string(), time_to_fail),
+    ];
+    if let Some(path) = triage.and_then(|t| t.path.as_ref()) {
+        rows.push(("Reproducer".to_string(), path.display().to_string()));
+    }
+    section("Reproduction", vec![Block::Table(rows)])
+}
+
+/// `Corpus::show` of the buffer, compared to the closest non-crashing buffer of the triage.
+fn structure<M: Corpus>(buffer: &[u8], triage: Option<&TriageResult>) -> Section {
+    let Some(corpus) = M::from_bytes(buffer.to_vec()) else {
+        return section(
+            "Structure",
+            vec![Block::Text(
+                "The buffer could not be parsed by the `Corpus` implementation.".to_string(),
+            )],
+        );
+    };
+    let show = corpus.show();
+    // the non-crashing buffer was selected for the buffer of the triage, so it is not compared
+    // to a reproducer which was changed afterwards, e.g. by `minimize`
+    let stale = triage.is_some_and(|t| {
+        t.closest_non_crashing.is_some() && t.buffer.as_deref() != Some(&hex::encode(buffer))
+    });
+    let base = triage
+        .filter(|_| !stale)
+        .and_then(|t| t.closest_non_crashing.as_ref())
+        .and_then(|hex| hex::decode(hex).ok())
+        .and_then(M::from_bytes)
+        .map(|c| c.show());
+    let base_lines: Vec<&str> = base
+        .as_deref()
+        .map(|b| b.lines().collect())
+        .unwrap_or_default();
+    let lines = show
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            let changed = base.is_some() && base_lines.get(i) != Some(&line);
+            let base_line = base_lines.get(i).copied().unwrap_or("");
+            (line.to_string(), changed.then(|| base_line.to_string()))
+        })
+        .collect();
+    let mut blocks = vec![Block::Diff(lines)];
+    if base.is_some() {
+        blocks.push(Block::Text(
+            "Lines which differ from the most similar buffer which did not crash the target are highlighted.".to_string(),
+        ));
+    }
+    if stale {
+        blocks.push(Block::Text(
+            "The reproducer was changed after the triage, so it is not compared to the most similar buffer which did not crash the target.".to_string(),
+        ));
+    }
+    section("Structure", blocks)
+}
+
+fn stats(stats: &Stats) -> Section {
+    section(
+        "Stats at crash time",
+        vec![Block::Table(vec![
+            (
+                "Started".to_string(),
+                format_time(stats.started.map(|s| s.to_utc())),
+            ),
+            (
+                "Total executions".to_string(),
+                stats.total_executions.to_string(),
+            ),
+            (
+                "Executions per second".to_string(),
+                stats.executions_per_second.get().to_string(),
+            ),
+            ("Cycles done".to_string(), stats.cylcles_done.to_string()),
+            ("Corpus count".to_string(), stats.corpus_count.to_string()),
+            (
+                "Unique responses".to_string(),
+                stats.total_unique_responses.to_string(),
+            ),
+            (
+                "Unique crashes".to_string(),
+                stats.total_crashes.to_string(),
+            ),
+            ("Timeouts".to_string(), stats.total_timeouts.to_string()),
+            ("Workers".to_string(), stats.workers.to_string()),
+        ])],
+    )
+}
+
+fn format_time(time: Option<DateTime<Utc>>) -> String {
+    time.map_or_else(
+        || "unknown".to_string(),
+        |time| time.to_rfc3339_opts(SecondsFormat::Micros, true),
+    )
+}
+
+/// Classic hexdump with 16 bytes per line.
+fn hexdump(buffer: &[u8]) -> String {
+    let mut dump = String::new();
+    for (i, chunk) in buffer.chunks(16).enumerate() {
+        let hex: Vec<String> = chunk.iter().map(|b| format!("{b:02x}")).collect();
+        let ascii: String = chunk
+            .iter()
+            .map(|b| {
+                if b.is_ascii_graphic() || *b == b' ' {
+                    char::from(*b)
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        let _ = writeln!(dump, "{:08x}  {:<47}  |{ascii}|", i * 16, hex.join(" "));
+    }
+    dump
+}
+
+fn render_markdown(id: usize, sections: &[Section]) -> String {
+    let mut md = format!("# Crash {id}\n");
+    for section in sections {
+        let _ = write!(md, "\n## {}\n\n", section.title);
+        for block in &section.blocks {
+            match block {
+                Block::Table(rows) => {
+                    md.push_str("| | |\n|---|---|\n");
+                    for (key, value) in rows {
+                        let _ = writeln!(md, "| {key} | {} |", value.replace('|', "\\|"));
+                    }
+                }
+                Block::Columns(header, rows) => {
+                    let _ = writeln!(md, "| {} |", header.join(" | "));
+                    let _ = writeln!(md, "|{}", "---|".repeat(header.len()));
+                    for row in rows {
+                        let _ = writeln!(md, "| {} |", row.join(" | "));
+                    }
+                }
+                Block::Code(code) => {
+                    let fence = fence(code);
+                    let _ = writeln!(md, "{fence}\n{}\n{fence}", code.trim_end());
+                }
+                Block::Diff(lines) => {
+                    let fence = fence(&lines_text(lines));
+                    let _ = writeln!(md, "{fence}diff");
+                    for (line, base) in lines {
+                        match base {
+                            Some(base) => {
+                                let _ = writeln!(md, "- {base}\n+ {line}");
+                            }
+                            None => {
+                                let _ = writeln!(md, "  {line}");
+                            }
+                        }
+                    }
+                    let _ = writeln!(md, "{fence}");
+                }
+                Block::Text(text) => {
+                    let _ = writeln!(md, "{text}");
+                }
+            }
+            md.push('\n');
+        }
+    }
+    md
+}
+
+/// Returns a code fence which is longer than every run of backticks in the content, so the
+/// content can not close the code block.
+fn fence(content: &str) -> String {
+    let longest = content
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+    "`".repeat(longest.max(2) + 1)
+}
+
+fn lines_text(lines: &[(String, Option<String>)]) -> String {
+    lines
+        .iter()
+        .flat_map(|(line, base)| [Some(line.as_str()), base.as_deref()])
+        .flatten()
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Escapes the text for HTML and XML.
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn render_html(id: usize, sections: &[Section]) -> String {
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Crash {id}</title>\n\
+         <style>\nbody {{ font-family: sans-serif; }}\n\
+         table {{ border-collapse: collapse; }}\n\
+         td, th {{ border: 1px solid #ccc; padding: 2px 8px; text-align: left; }}\n\
+         pre {{ background: #f6f8fa; padding: 8px; }}\n\
+         .removed {{ background: #ffd7d5; }}\n.added {{ background: #fff3b0; font-weight: bold; }}\n\
+         </style>\n</head>\n<body>\n<h1>Crash {id}</h1>\n"
+    );
+    for section in sections {
+        let _ = writeln!(html, "<h2>{}</h2>", escape(&section.title));
+        for block in &section.blocks {
+            match block {
+                Block::Table(rows) => {
+                    html.push_str("<table>\n");
+                    for (key, value) in rows {
+                        let _ = writeln!(
+                            html,
+                            "<tr><th>{}</th><td>{}</td></tr>",
+                            escape(key),
+                            escape(value)
+                        );
+                    }
+                    html.push_str("</table>\n");
+                }
+                Block::Columns(header, rows) => {
+                    html.push_str("<table>\n<tr>");
+                    for column in header {
+                        let _ = write!(html, "<th>{}</th>", escape(column));
+                    }
+                    html.push_str("</tr>\n");
+                    for row in rows {
+                        html.push_str("<tr>");
+                        for cell in row {
+                            let _ = write!(html, "<td><code>{}</code></td>", escape(cell));
+                        }
+                        html.push_str("</tr>\n");
+                    }
+                    html.push_str("</table>\n");
+                }
+                Block::Code(code) => {
+                    let _ = writeln!(html, "<pre>{}</pre>", escape(code.trim_end()));
+                }
+                Block::Diff(lines) => {
+                    html.push_str("<pre>");
+                    for (line, base) in lines {
+                        match base {
+                            Some(base) => {
+                                let _ = writeln!(
+                                    html,
+                                    "<span class=\"removed\">- {}</span>\n<span class=\"added\">+ {}</span>",
+                                    escape(base),
+                                    escape(line)
+                                );
+                            }
+                            None => {
+                                let _ = writeln!(html, "  {}", escape(line));
+                            }
+                        }
+                    }
+                    html.push_str("</pre>\n");
+                }
+                Block::Text(text) => {
+                    let _ = writeln!(html, "<p>{}</p>", escape(text));
+                }
+            }
+        }
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{Message, TempOutput, legacy_crash};
+    use crate::types::save_reproducer;
+
+    #[test]
+    fn hexdump_lines() {
+        let buffer: Vec<u8> = (0x3c..0x50).collect();
+        assert_eq!(
+            hexdump(&buffer),
+            "00000000  3c 3d 3e 3f 40 41 42 43 44 45 46 47 48 49 4a 4b  |<=>?@ABCDEFGHIJK|\n\
+             00000010  4c 4d 4e 4f                                      |LMNO|\n"
+        );
+    }
+
+    #[test]
+    fn fences_are_longer_than_the_content() {
+        assert_eq!(fence("no backticks"), "```");
+        assert_eq!(fence("a ``` b"), "````");
+        assert_eq!(fence("`````"), "``````");
+    }
+
+    #[test]
+    fn renders_a_report() {
+        let temp = TempOutput::with_legacy_crashes("report", &[legacy_crash(b"\x01```", &[], 0)]);
+        let output = &temp.output;
+        let triage = serde_json::json!({
+            "crash_id": 0,
+            "reproduced": true,
+            "buffer": "01606060",
+            "path": null,
+            "closest_non_crashing": "01106060",
+        });
+        std::fs::write(
+            output.get_crash_dir(0).join("triage.json"),
+            triage.to_string(),
+        )
+        .expect("write triage");
+
+        let report = |output: &Output| {
+            let paths = report_from_output_dir::<Message>(output, ReportFormat::Markdown, Some(0))
+                .expect("report");
+            std::fs::read_to_string(&paths[0]).expect("read report")
+        };
+        let md = report(output);
+        assert!(md.starts_with("# Crash 0\n\n## Summary\n"));
+        assert!(md.contains("| Status | reproduced |"));
+        assert!(md.contains("```diff\n- [1, 16, 96, 96]\n+ [1, 96, 96, 96]\n```\n"));
+        assert!(md.contains("## Buffer (4 bytes)\n\n````\n00000000  01 60 60 60"));
+        assert!(md.contains("|.```|\n````\n"));
+        assert!(md.contains("## Stats at crash time"));
+
+        // the minimized reproducer is not compared to the non-crashing buffer of the triage
+        save_reproducer(&output.get_crash_dir(0), &[vec![1, 0x60]]).expect("save reproducer");
+        let md = report(output);
+        assert!(md.contains("```diff\n  [1, 96]\n```\n"));
+        assert!(md.contains("The reproducer was changed after the triage"));
+        assert!(md.contains("## Buffer (2 bytes)"));
+    }
+}
@@ -82,3 +82,40 @@ pub(crate) fn hamming_distance(vec1: &[u8], vec2: &[u8]) -> Option<usize> {
 
     Some(distance)
 }
+
+/// Returns the buffer with the smallest hamming distance to `buffer`, ignoring identical ones.
+pub(crate) fn closest_buffer<'a>(buffer: &[u8], candidates: &'a [Vec<u8>]) -> Option<&'a Vec<u8>> {
+    candidates
+        .iter()
+        .filter_map(|candidate| Some((hamming_distance(candidate, buffer)?, candidate)))
+        .filter(|(hamming, _)| *hamming > 0)
+        .min_by_key(|(hamming, _)| *hamming)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Returns the buffer with the smallest hamming distance to `buffer`. In case no buffer has the
+/// same length, the buffer sharing the longest common prefix and suffix is returned instead.
+pub(crate) fn closest_any_buffer<'a>(
+    buffer: &[u8],
+    candidates: &'a [Vec<u8>],
+) -> Option<&'a Vec<u8>> {
+    closest_buffer(buffer, candidates).or_else(|| {
+        candidates
+            .iter()
+            .filter(|candidate| candidate.len() != buffer.len())
+            .min_by_key(|candidate| {
+                let prefix = buffer
+                    .iter()
+                    .zip(candidate.iter())
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                let suffix = buffer[prefix..]
+                    .iter()
+                    .rev()
+                    .zip(candidate[prefix..].iter().rev())
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                buffer.len() + candidate.len() - 2 * (prefix + suffix)
+            })
+    })
+}
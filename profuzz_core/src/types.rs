@@ -1,33 +1,488 @@
-use crate::error::ProFuzzResult;
+use crate::error::{ProFuzzError, ProFuzzResult};
 use crate::fuzz::stats::Stats;
 use crate::output::Output;
+use crate::traits::TransportMetadata;
+use crate::triage::dynamic::Reproducibility;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 
 #[derive(Serialize, Deserialize)]
 /// This represents a crash that stores all send buffers since the last sucessfull healthcheck.
+/// Every crash is stored in its own directory `crashes/<id>/` with the metadata in `crash.json`,
+/// the triggering buffer in `buffer.bin` and the history in `history.bin`.
 pub(crate) struct Crash {
-    // mutations_since_reseeding: usize,
+    pub(crate) id: usize,
+    /// The last buffer sent before the crash was detected
+    #[serde(skip)]
     pub(crate) buffer: Vec<u8>,
-    // state: Option<MutationState>,
-    pub(crate) last_send_buffers: Vec<Vec<u8>>,
+    /// The last buffers sent to the target, the oldest first
+    #[serde(skip)]
+    pub(crate) history: Vec<SentBuffer>,
+    /// Time of the failed health check which detected the crash
+    #[serde(default)]
+    pub(crate) detected_at: Option<DateTime<Utc>>,
+    /// Time when the buffer which triggered the crash was sent
+    #[serde(default)]
+    pub(crate) sent_at: Option<DateTime<Utc>>,
+    /// How the health check failed, the error message or `unhealthy`
+    #[serde(default)]
+    pub(crate) healthcheck_failure: Option<String>,
+    /// Sending the triggering buffer failed, so the health check ran right after it was sent
+    #[serde(default)]
+    pub(crate) send_failed: bool,
+    /// Time of the last successful health check of the crashed target
+    #[serde(default)]
+    pub(crate) last_healthcheck_at: Option<DateTime<Utc>>,
     pub(crate) stats: Stats,
+    /// Index of the worker whose target crashed
+    #[serde(default)]
+    pub(crate) worker: usize,
+    /// Title of the transport of the crashed target
+    #[serde(default)]
+    pub(crate) target: String,
+    /// How reliably the crash was reproduced during the triage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) reproducibility: Option<Reproducibility>,
+    /// Cluster of crashes which are likely caused by the same bug
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) cluster: Option<usize>,
 }
 
 impl Crash {
-    pub(crate) fn load(output: &Output) -> ProFuzzResult<Vec<Crash>> {
-        let path = output.get_crash_file();
+    /// Loads all crashes stored in the output directory ordered by their id.
+    pub(crate) fn load_all(output: &Output) -> ProFuzzResult<Vec<Crash>> {
+        let mut crashes = vec![];
+        for id in output.get_crash_ids()? {
+            crashes.push(Self::load(output, id)?);
+        }
+        Ok(crashes)
+    }
+
+    pub(crate) fn load(output: &Output, id: usize) -> ProFuzzResult<Crash> {
+        let mut crash = Self::load_meta(output, id)?;
+        let dir = output.get_crash_dir(id);
+        crash.history = decode_history(&std::fs::read(dir.join(CRASH_HISTORY_FILE))?)?;
+        Ok(crash)
+    }
+
+    /// Returns the number of buffers in the history of the crash without decoding it.
+    pub(crate) fn history_len(output: &Output, id: usize) -> ProFuzzResult<usize> {
+        let encoded = std::fs::read(output.get_crash_dir(id).join(CRASH_HISTORY_FILE))?;
+        let mut rest = encoded.as_slice();
+        let mut len = 0;
+        while let Some((_, tail)) = rest.split_first_chunk::<8>()
+            && let Some((size, tail)) = tail.split_first_chunk::<4>()
+        {
+            rest = tail
+                .get(u32::from_le_bytes(*size) as usize..)
+                .unwrap_or_default();
+            len += 1;
+        }
+        Ok(len)
+    }
+
+    /// Loads the crash without its history, which can be large.
+    pub(crate) fn load_meta(output: &Output, id: usize) -> ProFuzzResult<Crash> {
+        let dir = output.get_crash_dir(id);
+        let content = std::fs::read_to_string(dir.join(CRASH_META_FILE))?;
+        let mut crash: Crash = serde_json::from_str(&content)?;
+        crash.buffer = std::fs::read(dir.join(CRASH_BUFFER_FILE))?;
+        Ok(crash)
+    }
+
+    /// Stores the crash in a temporary directory first and then renames it, so an interrupted
+    /// write does not leave an incomplete crash behind.
+    pub(crate) fn save(&self, output: &Output) -> ProFuzzResult<()> {
+        let dir = output.get_crash_dir(self.id);
+        let tmp = dir.with_extension("tmp");
+        if tmp.is_dir() {
+            std::fs::remove_dir_all(&tmp)?;
+        }
+        std::fs::create_dir_all(&tmp)?;
+        std::fs::write(
+            tmp.join(CRASH_META_FILE),
+            serde_json::to_string_pretty(self)?,
+        )?;
+        std::fs::write(tmp.join(CRASH_BUFFER_FILE), &self.buffer)?;
+        std::fs::write(tmp.join(CRASH_HISTORY_FILE), encode_history(&self.history))?;
+        std::fs::rename(tmp, dir)?;
+        Ok(())
+    }
+
+    /// Only updates the metadata of an already stored crash.
+    pub(crate) fn save_meta(&self, output: &Output) -> ProFuzzResult<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        Output::write_atomic(
+            &output.get_crash_dir(self.id).join(CRASH_META_FILE),
+            content.as_bytes(),
+        )
+    }
+
+    /// Moves the crashes of the single `crashes.json` used by older versions into their own
+    /// directories. The old file is kept as `crashes.json.migrated` once every crash is stored.
+    /// Crashes which were already moved by an interrupted migration are skipped.
+    pub(crate) fn migrate(output: &Output) -> ProFuzzResult<()> {
+        let path = output.get_legacy_crash_file();
+        if !path.is_file() {
+            return Ok(());
+        }
+        let content = std::fs::read_to_string(&path)?;
+        let legacy: Vec<LegacyCrash> = serde_json::from_str(&content)?;
+        let migrated: Vec<Crash> = Self::load_all(output)?
+            .into_iter()
+            .filter(|crash| crash.detected_at.is_none())
+            .collect();
+        let mut id = output.next_crash_id()?;
+        let mut count = 0;
+        for crash in legacy {
+            let history = crash
+                .last_send_buffers
+                .into_iter()
+                .rev()
+                .map(|buffer| SentBuffer {
+                    sent_at: None,
+                    buffer,
+                })
+                .collect::<Vec<_>>();
+            let done = migrated.iter().any(|other| {
+                other.worker == crash.worker
+                    && other.target == crash.target
+                    && other.buffer == crash.buffer
+                    && other.history == history
+            });
+            if done {
+                continue;
+            }
+            Crash {
+                id,
+                buffer: crash.buffer,
+                history,
+                detected_at: None,
+                sent_at: None,
+                healthcheck_failure: None,
+                send_failed: false,
+                last_healthcheck_at: None,
+                stats: crash.stats,
+                worker: crash.worker,
+                target: crash.target,
+                reproducibility: None,
+                cluster: None,
+            }
+            .save(output)?;
+            id += 1;
+            count += 1;
+        }
+        std::fs::rename(&path, path.with_extension("json.migrated"))?;
+        tracing::info!("Migrated crashes.json into {count} crash directories.");
+        Ok(())
+    }
+}
+
+pub(crate) const CRASH_META_FILE: &str = "crash.json";
+pub(crate) const CRASH_BUFFER_FILE: &str = "buffer.bin";
+const CRASH_HISTORY_FILE: &str = "history.bin";
+
+#[derive(Serialize, Deserialize)]
+/// A buffer after which the target did not respond in time. Every timeout is stored in its own
+/// directory `timeouts/<id>/` with the metadata in `timeout.json` and the buffer in
+/// `buffer.bin`.
+pub(crate) struct Timeout {
+    pub(crate) id: usize,
+    /// The buffer which was written or whose response was read when the timeout occurred
+    #[serde(skip)]
+    pub(crate) buffer: Vec<u8>,
+    /// Time when the timeout occurred
+    pub(crate) detected_at: DateTime<Utc>,
+    /// The timeout occurred while writing the buffer, otherwise while reading the response
+    pub(crate) writing: bool,
+    /// Index of the worker whose target timed out
+    pub(crate) worker: usize,
+    /// Title of the transport of the target
+    pub(crate) target: String,
+}
+
+impl Timeout {
+    /// Loads the timeout including its buffer.
+    pub(crate) fn load(output: &Output, id: usize) -> ProFuzzResult<Timeout> {
+        let dir = output.get_timeout_dir(id);
+        let content = std::fs::read_to_string(dir.join(TIMEOUT_META_FILE))?;
+        let mut timeout: Timeout = serde_json::from_str(&content)?;
+        timeout.buffer = std::fs::read(dir.join(CRASH_BUFFER_FILE))?;
+        Ok(timeout)
+    }
+
+    /// Stores the timeout like `Crash::save`, so an interrupted write does not leave an
+    /// incomplete timeout behind.
+    pub(crate) fn save(&self, output: &Output) -> ProFuzzResult<()> {
+        let dir = output.get_timeout_dir(self.id);
+        let tmp = dir.with_extension("tmp");
+        if tmp.is_dir() {
+            std::fs::remove_dir_all(&tmp)?;
+        }
+        std::fs::create_dir_all(&tmp)?;
+        std::fs::write(
+            tmp.join(TIMEOUT_META_FILE),
+            serde_json::to_string_pretty(self)?,
+        )?;
+        std::fs::write(tmp.join(CRASH_BUFFER_FILE), &self.buffer)?;
+        std::fs::rename(tmp, dir)?;
+        Ok(())
+    }
+}
+
+const TIMEOUT_META_FILE: &str = "timeout.json";
+
+#[derive(Deserialize)]
+/// A crash as stored by older versions in the `crashes.json`.
+struct LegacyCrash {
+    buffer: Vec<u8>,
+    /// The newest buffer first
+    last_send_buffers: Vec<Vec<u8>>,
+    stats: Stats,
+    #[serde(default)]
+    worker: usize,
+    #[serde(default)]
+    target: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A buffer sent to the target.
+pub(crate) struct SentBuffer {
+    /// Wall-clock time when the buffer was sent, this is unknown for migrated crashes
+    pub(crate) sent_at: Option<DateTime<Utc>>,
+    pub(crate) buffer: Vec<u8>,
+}
+
+/// Encodes the buffers as a sequence of the send time in nanoseconds since the unix epoch as
+/// i64 (0 if unknown) and the length as u32 followed by the bytes, all little endian.
+fn encode_history(history: &[SentBuffer]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(history.iter().map(|b| b.buffer.len() + 12).sum());
+    for sent in history {
+        let sent_at = sent
+            .sent_at
+            .and_then(|time| time.timestamp_nanos_opt())
+            .unwrap_or(0);
+        let len = u32::try_from(sent.buffer.len()).unwrap_or(u32::MAX);
+        encoded.extend_from_slice(&sent_at.to_le_bytes());
+        encoded.extend_from_slice(&len.to_le_bytes());
+        encoded.extend_from_slice(&sent.buffer[..len as usize]);
+    }
+    encoded
+}
+
+fn decode_history(mut encoded: &[u8]) -> ProFuzzResult<Vec<SentBuffer>> {
+    let corrupt = || ProFuzzError::Custom {
+        err_msg: "Corrupt crash history".to_string(),
+    };
+    let mut history = vec![];
+    while !encoded.is_empty() {
+        let (sent_at, rest) = encoded.split_first_chunk::<8>().ok_or_else(corrupt)?;
+        let (len, rest) = rest.split_first_chunk::<4>().ok_or_else(corrupt)?;
+        let sent_at = i64::from_le_bytes(*sent_at);
+        let len = u32::from_le_bytes(*len) as usize;
+        if rest.len() < len {
+            return Err(corrupt());
+        }
+        history.push(SentBuffer {
+            sent_at: (sent_at != 0).then(|| DateTime::from_timestamp_nanos(sent_at)),
+            buffer: rest[..len].to_vec(),
+        });
+        encoded = &rest[len..];
+    }
+    Ok(history)
+}
+
+/// Stores the buffers which reproduce a crash in the crash directory. A single buffer is stored
+/// as `reproducer.bin`, a sequence as `reproducer.<i>.bin`. Returns the paths of the files.
+pub(crate) fn save_reproducer(dir: &Path, sequence: &[Vec<u8>]) -> ProFuzzResult<Vec<PathBuf>> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if is_reproducer_file(&path) {
+            std::fs::remove_file(path)?;
+        }
+    }
+    let mut paths = vec![];
+    for (i, buffer) in sequence.iter().enumerate() {
+        let path = if sequence.len() == 1 {
+            dir.join("reproducer.bin")
+        } else {
+            dir.join(format!("reproducer.{i}.bin"))
+        };
+        std::fs::write(&path, buffer)?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// Loads the reproducer stored with `save_reproducer`.
+pub(crate) fn load_reproducer(dir: &Path) -> ProFuzzResult<Option<Vec<Vec<u8>>>> {
+    let files = reproducer_files(dir);
+    if files.is_empty() {
+        return Ok(None);
+    }
+    let mut sequence = vec![];
+    for file in files {
+        sequence.push(std::fs::read(file)?);
+    }
+    Ok(Some(sequence))
+}
+
+/// Returns the files of the reproducer stored with `save_reproducer` in their order.
+pub(crate) fn reproducer_files(dir: &Path) -> Vec<PathBuf> {
+    let single = dir.join("reproducer.bin");
+    if single.is_file() {
+        return vec![single];
+    }
+    let mut files = vec![];
+    loop {
+        let path = dir.join(format!("reproducer.{}.bin", files.len()));
+        if !path.is_file() {
+            return files;
+        }
+        files.push(path);
+    }
+}
+
+fn is_reproducer_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "bin")
+        && path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with("reproducer."))
+}
+
+#[derive(Serialize, Deserialize, Default)]
+/// The fuzzing queue as stored in the output directory, so a session can be resumed.
+pub(crate) struct SavedQueue {
+    pub(crate) entries: Vec<SavedQueueEntry>,
+    /// Position of the element in the queue which is currently used as mutation root
+    pub(crate) position: usize,
+    /// Hashes of all responses seen so far
+    pub(crate) unique_responses: Vec<Vec<u8>>,
+}
+
+#[derive(Serialize, Deserialize)]
+/// A single queue element with the metadata why it was added to the queue.
+pub(crate) struct SavedQueueEntry {
+    pub(crate) corpus: Vec<u8>,
+    /// Hash of the unique response which added this corpus to the queue. This is `None` for
+    /// the initial corpus files.
+    pub(crate) response_hash: Option<Vec<u8>>,
+    /// Total executions at the time the corpus was added to the queue
+    pub(crate) found_at_execution: u64,
+}
+
+impl SavedQueue {
+    pub(crate) fn load(output: &Output) -> ProFuzzResult<Option<SavedQueue>> {
+        let path = output.get_queue_file();
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(path)?;
+        Ok(Some(serde_json::from_str(&content)?))
+    }
+    pub(crate) fn save(&self, output: &Output) -> ProFuzzResult<()> {
+        let content = serde_json::to_string(self)?;
+        Output::write_atomic(&output.get_queue_file(), content.as_bytes())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+/// Checkpoint of the RNG used by the mutator. Starting with the same seed reproduces a run,
+/// while the word position allows to continue the stream when resuming.
+pub(crate) struct RngState {
+    pub(crate) seed: u64,
+    /// Word position of the RNG stream of each worker
+    pub(crate) word_positions: Vec<u128>,
+}
+
+impl RngState {
+    pub(crate) fn load(output: &Output) -> ProFuzzResult<Option<RngState>> {
+        let path = output.get_rng_file();
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(path)?;
+        Ok(Some(serde_json::from_str(&content)?))
+    }
+    pub(crate) fn save(&self, output: &Output) -> ProFuzzResult<()> {
+        let content = serde_json::to_string(self)?;
+        Output::write_atomic(&output.get_rng_file(), content.as_bytes())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+/// The transport of a worker, so crashes can be reproduced without the fuzzer.
+pub(crate) struct TargetInfo {
+    pub(crate) worker: usize,
+    pub(crate) title: String,
+    pub(crate) transport: TransportMetadata,
+}
+
+impl TargetInfo {
+    pub(crate) fn load_all(output: &Output) -> ProFuzzResult<Vec<TargetInfo>> {
+        let path = output.get_target_file();
         if !path.is_file() {
             return Ok(vec![]);
         }
         let content = std::fs::read_to_string(path)?;
         Ok(serde_json::from_str(&content)?)
     }
-    pub(crate) fn save(self, output: &Output) -> ProFuzzResult<()> {
-        let path = output.get_crash_file();
-        let mut current = Self::load(output)?;
-        current.push(self);
-        let content = serde_json::to_string(&current)?;
-        std::fs::write(path, content)?;
-        Ok(())
+
+    pub(crate) fn save_all(targets: &[TargetInfo], output: &Output) -> ProFuzzResult<()> {
+        let content = serde_json::to_string_pretty(targets)?;
+        Output::write_atomic(&output.get_target_file(), content.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{TempOutput, legacy_crash};
+
+    #[test]
+    fn history_roundtrip() {
+        let history: Vec<SentBuffer> = [vec![], vec![1, 2, 3], vec![0xff; 300]]
+            .into_iter()
+            .enumerate()
+            .map(|(i, buffer)| SentBuffer {
+                sent_at: (i > 0).then(Utc::now),
+                buffer,
+            })
+            .collect();
+        let encoded = encode_history(&history);
+        assert_eq!(decode_history(&encoded).ok(), Some(history));
+        assert!(decode_history(&encoded[..encoded.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn migrate_legacy_crashes() {
+        let temp = TempOutput::with_legacy_crashes(
+            "migrate",
+            &[
+                legacy_crash(&[1], &[&[3], &[2], &[1]], 0),
+                legacy_crash(&[4], &[&[4]], 1),
+            ],
+        );
+        let output = &temp.output;
+        let crashes = Crash::load_all(output).expect("load crashes");
+        assert_eq!(crashes.len(), 2);
+        let history: Vec<Vec<u8>> = crashes[0]
+            .history
+            .iter()
+            .map(|b| b.buffer.clone())
+            .collect();
+        assert_eq!(history, vec![vec![1], vec![2], vec![3]]);
+        assert_eq!(Crash::history_len(output, 0).expect("history length"), 3);
+        assert_eq!(crashes[1].id, 1);
+        assert_eq!(crashes[1].worker, 1);
+        assert!(!output.get_path().join("crashes.json").exists());
+
+        // a re-run after an interrupted migration does not duplicate the stored crashes
+        let path = output.get_path().join("crashes.json");
+        std::fs::rename(path.with_extension("json.migrated"), &path).expect("restore crashes");
+        Crash::migrate(output).expect("migrate again");
+        assert_eq!(output.get_crash_ids().expect("crash ids"), vec![0, 1]);
     }
 }